@@ -1,14 +1,17 @@
-//! Sample rate utilities.
+//! Sample rate and tempo utilities.
 
-use crate::{Seconds, Hz};
+use crate::{Seconds, Hz, Bpm, Beats};
 
-/// Stores the current sample rate.
+pub(crate) const DEFAULT_TEMPO: Bpm = 120.0;
+
+/// Stores the current sample rate and host tempo.
 ///
 /// Ideally, we want to have only one of these per plugin instance.
 #[derive(Debug, Clone, Copy)]
 pub struct Clock {
     pub sample_rate: Hz,
     pub tick: Seconds,
+    pub tempo: Bpm,
 }
 
 impl Clock {
@@ -17,15 +20,34 @@ impl Clock {
         Self {
             sample_rate,
             tick: 1.0 / sample_rate,
+            tempo: DEFAULT_TEMPO,
         }
     }
 
     pub fn nyquist(&self) -> f64 {
         self.sample_rate * 0.5
     }
+
+    /// Set the host tempo; pass the clock to [SetClock::set_clock] afterwards
+    /// so tempo-synced components pick up the change.
+    pub fn set_tempo(&mut self, tempo: Bpm) {
+        crate::check_float_pos!(tempo);
+        self.tempo = tempo;
+    }
+
+    /// Convert a duration in beats to Hz at the current tempo.
+    pub fn beats_to_hz(&self, beats: Beats) -> Hz {
+        beats_to_hz(self.tempo, beats)
+    }
+}
+
+/// Convert a duration in beats to Hz at the given tempo,
+/// for specs that cache the tempo rather than the whole clock.
+pub(crate) fn beats_to_hz(tempo: Bpm, beats: Beats) -> Hz {
+    tempo / (60.0 * beats)
 }
 
-/// Trait for components that need to know current sample rate.
+/// Trait for components that need to know current sample rate or tempo.
 pub trait SetClock {
     fn set_clock(&mut self, clock: &Clock);
 }
@@ -38,16 +60,73 @@ impl<T: SetClock, const N: usize> SetClock for [T; N] {
     }
 }
 
+/// Length of a note, from a whole note down to a 64th.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum NoteValue {
+    Whole,
+    Half,
+    #[default]
+    Quarter,
+    Eighth,
+    Sixteenth,
+    ThirtySecond,
+    SixtyFourth,
+}
+
+/// Modifier on a [NoteValue]'s length.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum NoteModifier {
+    #[default]
+    Straight,
+    /// one and a half times as long
+    Dotted,
+    /// two thirds as long
+    Triplet,
+}
+
+/// A musical division of the beat, for tempo-synced rates.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Division {
+    pub value: NoteValue,
+    pub modifier: NoteModifier,
+}
+
+impl Division {
+    pub const fn new(value: NoteValue, modifier: NoteModifier) -> Self {
+        Self { value, modifier }
+    }
+
+    /// Length of the division, in quarter notes.
+    pub fn beats(&self) -> Beats {
+        use NoteValue::*;
+        let beats = match self.value {
+            Whole        => 4.0,
+            Half         => 2.0,
+            Quarter      => 1.0,
+            Eighth       => 0.5,
+            Sixteenth    => 0.25,
+            ThirtySecond => 0.125,
+            SixtyFourth  => 0.0625,
+        };
+        match self.modifier {
+            NoteModifier::Straight => beats,
+            NoteModifier::Dotted   => beats * 1.5,
+            NoteModifier::Triplet  => beats * 2.0 / 3.0,
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
     #[test]
     fn test_clock() {
-        assert_eq!(std::mem::size_of::<Clock>(), 16);
+        assert_eq!(std::mem::size_of::<Clock>(), 24);
         let clock = Clock::new(44_100.0);
         assert_eq!(clock.sample_rate, 44_100.0);
         assert_eq!(clock.tick, 1.0 / 44_100.0);
         assert_eq!(clock.nyquist(), 44_100.0 * 0.5);
+        assert_eq!(clock.tempo, 120.0);
     }
     #[test]
     #[should_panic]
@@ -59,4 +138,23 @@ mod test {
     fn test_bounds_neg() {
         Clock::new(-1.0);
     }
+    #[test]
+    #[should_panic]
+    fn test_tempo_zero() {
+        Clock::new(44_100.0).set_tempo(0.0);
+    }
+    #[test]
+    fn test_division() {
+        use NoteValue::*;
+        use NoteModifier::*;
+        assert_eq!(std::mem::size_of::<Division>(), 2);
+        assert_eq!(Division::new(Whole, Straight).beats(), 4.0);
+        assert_eq!(Division::new(Quarter, Dotted).beats(), 1.5);
+        assert_eq!(Division::new(Eighth, Triplet).beats(), 1.0 / 3.0);
+        assert_eq!(Division::new(SixtyFourth, Straight).beats(), 0.0625);
+        let mut clock = Clock::new(44_100.0);
+        assert_eq!(clock.beats_to_hz(Division::default().beats()), 2.0);
+        clock.set_tempo(90.0);
+        assert_eq!(clock.beats_to_hz(Division::new(Half, Straight).beats()), 0.75);
+    }
 }
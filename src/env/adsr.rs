@@ -1,43 +1,99 @@
-use crate::{Scale, Seconds, Gen, F};
-use crate::clock::{Clock, SetClock};
+use crate::{Scale, Seconds, Hz, Gen, F};
+use crate::clock::{Clock, SetClock, Division};
+use crate::trigger::Trigger;
 
-use super::{TimeStage, HoldRelease};
+use super::{TimeStage, TriggerMode, ReleaseMode, EnvLoop, LoopRate, EnvState, EnvStatus, HoldRelease, declick, preview};
 
 /// Marker for which stage of an ADSR env we're in.
 ///
 /// Used by both [Adsr] and [super::ExpAdsr].
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum AdsrStage {
+    #[default]
     A, D, S, R,
 }
 
-impl Default for AdsrStage {
-    fn default() -> Self { Self::A }
-}
-
 /// Envelope generator for ADSR envs with linear segments.
 #[derive(Debug, Default)]
 pub struct Adsr {
     stage: AdsrStage,
     next_val: Scale,
+    ramping: bool,
+    // progress through an EnvLoop::Ads loop:
+    cycle: F,
+    // level the release started from:
+    from: Scale,
+}
+
+impl Adsr {
+    pub fn stage(&self) -> AdsrStage {
+        self.stage
+    }
+
+    pub fn finished(&self) -> bool {
+        self.stage == AdsrStage::R && self.next_val < 0.0
+    }
+}
+
+impl EnvStatus for Adsr {
+    fn state(&self) -> EnvState {
+        match self.stage {
+            AdsrStage::A => EnvState::Attack,
+            AdsrStage::D => EnvState::Decay,
+            AdsrStage::S => EnvState::Sustain,
+            AdsrStage::R if self.finished() => EnvState::Finished,
+            AdsrStage::R => EnvState::Release,
+        }
+    }
 }
 
 impl HoldRelease for Adsr {
     fn hold(&mut self) {
         self.stage = AdsrStage::A;
+        self.ramping = false;
+        self.cycle = 0.0;
+        if self.next_val < 0.0 {
+            self.next_val = 0.0;
+        }
     }
     fn release(&mut self) {
         self.stage = AdsrStage::R;
+        self.from = self.next_val;
     }
     fn sustain(&mut self) {
         self.stage = AdsrStage::S;
     }
 }
 
+impl Trigger for Adsr {
+    type Spec = AdsrSpec;
+    fn trigger(&mut self, spec: &Self::Spec, from_silence: bool) {
+        let mode = spec.trigger_mode;
+        if mode.restarts(self.stage != AdsrStage::R, from_silence) {
+            let level = self.next_val;
+            self.hold();
+            match mode {
+                TriggerMode::Reset => self.next_val = 0.0,
+                TriggerMode::Ramp => self.ramping = level > 0.0,
+                TriggerMode::Retrigger | TriggerMode::Legato => (),
+            }
+        }
+    }
+}
+
 impl Gen<Scale> for Adsr {
     type Spec = AdsrSpec;
     fn gen(&mut self, spec: &Self::Spec) -> Scale {
         use AdsrStage::*;
+        if self.ramping {
+            match declick(&mut self.next_val, spec.tick) {
+                Some(val) => return val,
+                None => self.ramping = false,
+            }
+        }
+        if spec.looping == EnvLoop::Ads && self.stage != R && spec.loop_rate.advance(&mut self.cycle) {
+            self.stage = A;
+        }
         let queued_val = self.next_val;
         match self.stage {
             S => queued_val,
@@ -45,7 +101,7 @@ impl Gen<Scale> for Adsr {
                 if self.next_val < 0.0 {
                     0.0
                 } else {
-                    self.next_val -= spec.r.tick_over_time();
+                    self.next_val -= spec.r.tick_over_time() * spec.release_mode.scale(self.from);
                     queued_val
                 }
             },
@@ -61,7 +117,7 @@ impl Gen<Scale> for Adsr {
             },
             D => {
                 if self.next_val < spec.s {
-                    self.stage = S;
+                    self.stage = spec.looping.after_decay();
                     self.next_val = spec.s;
                     spec.s
                 } else {
@@ -74,13 +130,17 @@ impl Gen<Scale> for Adsr {
 }
 
 /// Spec for [Adsr].
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
 pub struct AdsrSpec {
     a: TimeStage,
     d: TimeStage,
     s: Scale,
     r: TimeStage,
     tick: Seconds,
+    trigger_mode: TriggerMode,
+    release_mode: ReleaseMode,
+    looping: EnvLoop,
+    loop_rate: LoopRate,
 }
 
 impl SetClock for AdsrSpec {
@@ -89,10 +149,33 @@ impl SetClock for AdsrSpec {
         self.a.set_tick(self.tick);
         self.d.set_tick(self.tick);
         self.r.set_tick(self.tick);
+        self.loop_rate.set_clock(clock);
     }
 }
 
 impl AdsrSpec {
+    crate::accessors!(trigger_mode, get_trigger_mode, set_trigger_mode, TriggerMode);
+    crate::accessors!(release_mode, get_release_mode, set_release_mode, ReleaseMode);
+    crate::accessors!(looping, get_loop, set_loop, EnvLoop);
+
+    pub fn get_loop_rate(&self) -> Hz {
+        self.loop_rate.rate()
+    }
+
+    /// Set how often an [EnvLoop::Ads] loop restarts, in Hz.
+    pub fn set_loop_rate(&mut self, rate: Hz) {
+        self.loop_rate.set_rate(rate);
+    }
+
+    pub fn get_loop_division(&self) -> Option<Division> {
+        self.loop_rate.division()
+    }
+
+    /// Sync the loop rate to the host tempo, or go back to the free rate in Hz with `None`.
+    pub fn set_loop_division(&mut self, division: Option<Division>) {
+        self.loop_rate.set_division(division);
+    }
+
     pub fn get(&self, stage: AdsrStage) -> F {
         use AdsrStage::*;
         match stage {
@@ -112,6 +195,19 @@ impl AdsrSpec {
             R => self.r.set(self.tick, val),
         };
     }
+
+    /// Render the env's shape into `out` without touching any live state,
+    /// holding for `gate` seconds and then releasing.
+    ///
+    /// The points are spread evenly over the gate and release time,
+    /// and produced by running an [Adsr] on a copy of this spec, clocked at one point per sample.
+    pub fn preview(&self, gate: Seconds, out: &mut [Scale]) {
+        crate::check_float_nonneg!(gate);
+        let total = gate + self.r.time();
+        let mut spec = self.clone();
+        spec.set_clock(&preview::clock(total, out.len(), self.loop_rate.tempo()));
+        preview::render::<Adsr>(&spec, preview::gate_points(gate, total, out.len()), out);
+    }
 }
 
 #[cfg(test)]
@@ -130,8 +226,8 @@ mod test {
     #[test]
     fn size() {
         use std::mem::size_of;
-        assert_eq!(size_of::<Adsr>(), 16);
-        assert_eq!(size_of::<AdsrSpec>(), 64);
+        assert_eq!(size_of::<Adsr>(), 32);
+        assert_eq!(size_of::<AdsrSpec>(), 112);
     }
     #[test]
     fn a_reaches_1() {
@@ -146,7 +242,7 @@ mod test {
     }
     #[test]
     fn d_reaches_s() {
-        let mut adsr = Adsr { stage: AdsrStage::D, next_val: 1.0, };
+        let mut adsr = Adsr { stage: AdsrStage::D, next_val: 1.0, ramping: false, cycle: 0.0, from: 0.0 };
         let spec = spec(0.0, 1.0, 0.5, 0.0);
         let mut output = adsr.gen(&spec);
         assert_eq!(output, 1.0);
@@ -157,7 +253,7 @@ mod test {
     }
     #[test]
     fn r_reaches_0() {
-        let mut adsr = Adsr { stage: AdsrStage::R, next_val: 0.5, };
+        let mut adsr = Adsr { stage: AdsrStage::R, next_val: 0.5, ramping: false, cycle: 0.0, from: 0.5 };
         let spec = spec(0.0, 0.0, 0.5, 1.0);
         let mut output = adsr.gen(&spec);
         assert!(output > 0.0);
@@ -166,4 +262,120 @@ mod test {
         }
         assert_eq!(output, 0.0);
     }
+    #[test]
+    fn test_trigger_modes() {
+        let mut spec = spec(0.005, 0.005, 0.5, 0.01);
+        spec.set_clock(&Clock::new(1000.0));
+        // trigger an env sitting at 0.5, either still held or releasing:
+        let mut run = |mode, stage, from_silence| {
+            let mut adsr = Adsr { stage, next_val: 0.5, ramping: false, cycle: 0.0, from: 0.5 };
+            spec.set_trigger_mode(mode);
+            adsr.trigger(&spec, from_silence);
+            let out: Vec<_> = (0..5).map(|_| adsr.gen(&spec)).collect();
+            out
+        };
+        use AdsrStage::*;
+        assert_eq!(run(TriggerMode::Reset, S, false), [0.0, 0.2, 0.4, 0.6000000000000001, 0.8]);
+        assert_eq!(run(TriggerMode::Retrigger, S, false), [0.5, 0.7, 0.8999999999999999, 1.0, 0.8]);
+        assert_eq!(run(TriggerMode::Ramp, S, false), [0.5, 0.0, 0.2, 0.4, 0.6000000000000001]);
+        // legato carries on while held, but starts over once released or from silence:
+        assert_eq!(run(TriggerMode::Legato, S, false), [0.5, 0.5, 0.5, 0.5, 0.5]);
+        assert_eq!(run(TriggerMode::Legato, R, false), [0.5, 0.7, 0.8999999999999999, 1.0, 0.8]);
+        assert_eq!(run(TriggerMode::Legato, S, true), [0.5, 0.7, 0.8999999999999999, 1.0, 0.8]);
+    }
+    #[test]
+    fn test_events() {
+        use crate::env::EnvEvent;
+        let mut adsr = Adsr::default();
+        let mut spec = spec(0.2, 0.2, 0.5, 0.2);
+        spec.set_clock(&Clock::new(10.0));
+        adsr.hold();
+        assert_eq!(adsr.state(), EnvState::Attack);
+        let events: Vec<_> = (0..6).map(|_| adsr.gen_event(&spec)).collect();
+        assert_eq!(events, [(0.0, None), (0.5, None), (1.0, None), (1.0, Some(EnvEvent::Decay)), (0.5, None), (0.5, Some(EnvEvent::Sustain))]);
+        adsr.release();
+        let events: Vec<_> = (0..3).map(|_| adsr.gen_event(&spec)).collect();
+        assert_eq!(events, [(0.5, None), (0.0, Some(EnvEvent::Finished)), (0.0, None)]);
+        assert!(adsr.finished());
+    }
+    #[test]
+    fn test_release_modes() {
+        let mut spec = spec(0.0, 0.0, 0.5, 0.4);
+        spec.set_clock(&Clock::new(10.0));
+        let mut run = |mode| {
+            let mut adsr = Adsr { stage: AdsrStage::S, next_val: 0.5, ramping: false, cycle: 0.0, from: 0.0 };
+            spec.set_release_mode(mode);
+            adsr.release();
+            let out: Vec<_> = (0..6).map(|_| adsr.gen(&spec)).collect();
+            out
+        };
+        // from half way up, a constant slope gets there in half the time:
+        assert_eq!(run(ReleaseMode::Slope), [0.5, 0.25, 0.0, 0.0, 0.0, 0.0]);
+        assert_eq!(run(ReleaseMode::Time), [0.5, 0.375, 0.25, 0.125, 0.0, 0.0]);
+    }
+    #[test]
+    fn test_loops() {
+        use crate::clock::{NoteValue, NoteModifier};
+        let mut spec = spec(0.2, 0.2, 0.5, 0.2);
+        spec.set_clock(&Clock::new(10.0));
+        let run = |spec: &AdsrSpec, n| {
+            let mut adsr = Adsr::default();
+            adsr.hold();
+            let out: Vec<_> = (0..n).map(|_| adsr.gen(spec)).collect();
+            out
+        };
+        // cycling between sustain and peak:
+        spec.set_loop(EnvLoop::Ad);
+        assert_eq!(run(&spec, 10), [0.0, 0.5, 1.0, 1.0, 0.5, 0.5, 0.5, 1.0, 1.0, 0.5]);
+        // restarting every second, holding at sustain in between:
+        spec.set_loop(EnvLoop::Ads);
+        spec.set_loop_rate(1.0);
+        assert_eq!(run(&spec, 14), [0.0, 0.5, 1.0, 1.0, 0.5, 0.5, 0.5, 0.5, 0.5, 0.5, 0.5, 1.0, 1.0, 0.5]);
+        // an eighth note at 120bpm is a quarter second:
+        spec.set_loop_division(Some(Division::new(NoteValue::Eighth, NoteModifier::Straight)));
+        assert_eq!(run(&spec, 8), [0.0, 0.5, 1.0, 1.0, 0.5, 1.0, 1.0, 0.5]);
+        // releasing stops the loop:
+        let mut adsr = Adsr::default();
+        adsr.hold();
+        adsr.gen(&spec);
+        adsr.release();
+        let out: Vec<_> = (0..6).map(|_| adsr.gen(&spec)).collect();
+        assert_eq!(out, [0.5, 0.0, 0.0, 0.0, 0.0, 0.0]);
+    }
+    #[test]
+    fn test_preview() {
+        let spec = spec(0.1, 0.1, 0.5, 0.2);
+        let mut out = [0.0; 12];
+        // 0.4s of gate, then 0.2s of release, at 20 points per second:
+        spec.preview(0.4, &mut out);
+        assert_eq!(out, [0.0, 0.5000000000000001, 1.0, 0.5, 0.5, 0.5, 0.5, 0.5, 0.5, 0.24999999999999994, 0.0, 0.0]);
+        // the live spec keeps its clock:
+        assert_eq!(spec.a.tick_over_time(), 0.00022675736961451246);
+    }
+    #[test]
+    fn test_preview_tempo() {
+        use crate::clock::{NoteValue, NoteModifier};
+        let mut spec = spec(0.1, 0.1, 0.5, 0.0);
+        spec.set_loop(EnvLoop::Ads);
+        spec.set_loop_division(Some(Division::new(NoteValue::Quarter, NoteModifier::Straight)));
+        let mut clock = Clock::new(10.0);
+        clock.set_tempo(60.0);
+        spec.set_clock(&clock);
+        // the preview loops at the host tempo, once a second, just as the live env does:
+        let mut out = [0.0; 20];
+        spec.preview(2.0, &mut out);
+        let mut adsr = Adsr::default();
+        adsr.hold();
+        assert_eq!(out, [(); 20].map(|_| adsr.gen(&spec)));
+        assert_eq!(out[10..14], [0.5, 1.0, 0.5, 0.5]);
+    }
+    #[test]
+    fn test_preview_release_mode() {
+        let mut spec = spec(0.1, 0.1, 0.25, 1.0);
+        spec.set_release_mode(ReleaseMode::Time);
+        let mut out = [0.0; 15];
+        // the release from sustain takes the full second, as it would live:
+        spec.preview(0.5, &mut out);
+        assert_eq!(out, [0.0, 1.0, 1.0, 0.25, 0.25, 0.25, 0.225, 0.2, 0.17500000000000002, 0.15000000000000002, 0.12500000000000003, 0.10000000000000003, 0.07500000000000004, 0.05000000000000004, 0.025000000000000036]);
+    }
 }
@@ -1,63 +1,132 @@
-use crate::{Scale, Seconds, Gen, F};
-use crate::clock::{Clock, SetClock};
+use crate::{Scale, Seconds, Hz, Gen, F};
+use crate::clock::{self, Clock, SetClock};
+use crate::trigger::Trigger;
 
-use super::{TimeStage, HoldRelease};
+use super::{TimeStage, ExpTimeStage, TriggerMode, EnvState, EnvStatus, HoldRelease, declick, preview};
 
-#[derive(Debug)]
-enum DaStage {
-    // Delay, Attack, Sustain:
-    D, A, S,
+/// Marker for which stage of a DA env we're in: delay, attack, sustain or release.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum DaStage {
+    #[default]
+    D, A, S, R,
 }
 
-impl Default for DaStage {
-    fn default() -> Self { Self::D }
-}
-
-/// Envelope generator for DA envs.
+/// Envelope generator for DA envs: a delay, then an attack up to full level,
+/// which holds until released.
 #[derive(Debug, Default)]
 pub struct DaEnv {
     stage: DaStage,
-    // this val measures either time or output scale, depending on stage:
-    val: F,
+    ramping: bool,
+    next_val: Scale,
+    // progress through the delay:
+    timer: Scale,
+}
+
+impl DaEnv {
+    pub fn stage(&self) -> DaStage {
+        self.stage
+    }
+
+    /// True once a release has reached zero.
+    pub fn finished(&self) -> bool {
+        self.stage == DaStage::R && self.next_val <= 0.0
+    }
+
+    fn compute_current(&mut self, stage: &ExpTimeStage) -> Scale {
+        let current_val = self.next_val;
+        self.next_val = stage.base() + self.next_val * stage.coef();
+        current_val
+    }
+
+    fn attack(&mut self, spec: &DaEnvSpec) -> Scale {
+        if self.next_val < 1.0 && spec.a.time() > 0.0 {
+            self.compute_current(&spec.a)
+        } else {
+            self.stage = DaStage::S;
+            self.next_val = 1.0;
+            1.0
+        }
+    }
+
+    // restart the delay, keeping the current level:
+    fn restart(&mut self) {
+        self.stage = DaStage::D;
+        self.timer = 0.0;
+        self.ramping = false;
+        if self.next_val < 0.0 {
+            self.next_val = 0.0;
+        }
+    }
+}
+
+impl EnvStatus for DaEnv {
+    fn state(&self) -> EnvState {
+        match self.stage {
+            DaStage::D | DaStage::A => EnvState::Attack,
+            DaStage::S => EnvState::Sustain,
+            DaStage::R if self.finished() => EnvState::Finished,
+            DaStage::R => EnvState::Release,
+        }
+    }
 }
 
 impl HoldRelease for DaEnv {
     fn hold(&mut self) {
-        self.stage = DaStage::D;
-        self.val = 0.0;
+        self.restart();
+        self.next_val = 0.0;
     }
     fn release(&mut self) {
-        self.stage = DaStage::S;
+        self.stage = DaStage::R;
     }
     fn sustain(&mut self) {
         self.stage = DaStage::S;
     }
 }
 
+impl Trigger for DaEnv {
+    type Spec = DaEnvSpec;
+    fn trigger(&mut self, spec: &Self::Spec, from_silence: bool) {
+        let mode = spec.trigger_mode;
+        if mode.restarts(self.stage != DaStage::R, from_silence) {
+            self.restart();
+            match mode {
+                TriggerMode::Reset => self.next_val = 0.0,
+                TriggerMode::Ramp => self.ramping = self.next_val > 0.0,
+                TriggerMode::Retrigger | TriggerMode::Legato => (),
+            }
+        }
+    }
+}
+
 impl Gen<Scale> for DaEnv {
     type Spec = DaEnvSpec;
     fn gen(&mut self, spec: &Self::Spec) -> Scale {
         use DaStage::*;
+        if self.ramping {
+            match declick(&mut self.next_val, spec.tick) {
+                Some(val) => return val,
+                None => self.ramping = false,
+            }
+        }
         match self.stage {
-            S => self.val,
             D => {
-                if self.val > spec.d {
-                    self.stage = A;
-                    self.val = 0.0;
+                // the delay holds the level we were triggered at:
+                if self.timer < 1.0 && spec.d.time() > 0.0 {
+                    self.timer += spec.d.tick_over_time();
+                    self.next_val
                 } else {
-                    self.val += spec.tick;
+                    self.stage = A;
+                    self.attack(spec)
                 }
-                0.0
             },
-            A => {
-                if self.val > 1.0 {
-                    self.stage = S;
-                    self.val = 1.0;
-                    1.0
+            A => self.attack(spec),
+            S => self.next_val,
+            R => {
+                if self.next_val > 0.0 && spec.r.time() > 0.0 {
+                    self.compute_current(&spec.r)
                 } else {
-                    let val = self.val;
-                    self.val += spec.a.tick_over_time();
-                    val
+                    self.next_val = 0.0;
+                    0.0
                 }
             },
         }
@@ -66,32 +135,103 @@ impl Gen<Scale> for DaEnv {
 
 /// Spec for [DaEnv].
 ///
-/// The interface only allows setting the total time, not D and A individually.
-/// D is hardcoded to be `2/3` of the total, and A `1/3`.
-#[derive(Debug, Default)]
+/// Attack and release are linear by default; set a curve to bend them both.
+#[derive(Debug, Clone)]
 pub struct DaEnvSpec {
-    d: Seconds,
-    a: TimeStage,
+    d: TimeStage,
+    a: ExpTimeStage,
+    r: ExpTimeStage,
     tick: Seconds,
+    sample_rate: Hz,
+    trigger_mode: TriggerMode,
+}
+
+impl Default for DaEnvSpec {
+    fn default() -> Self {
+        let mut spec = Self {
+            d: TimeStage::default(),
+            a: ExpTimeStage::rising(1.0),
+            r: ExpTimeStage::falling(0.0),
+            tick: 0.0,
+            sample_rate: 0.0,
+            trigger_mode: TriggerMode::default(),
+        };
+        spec.set_curve(0.0);
+        spec
+    }
 }
 
 impl SetClock for DaEnvSpec {
     fn set_clock(&mut self, clock: &Clock) {
         self.tick = clock.tick;
-        self.a.set_tick(self.tick);
+        self.sample_rate = clock.sample_rate;
+        self.d.set_tick(self.tick);
+        self.a.set_sample_rate(self.sample_rate);
+        self.r.set_sample_rate(self.sample_rate);
     }
 }
 
-// TODO or set D and A independently.
 impl DaEnvSpec {
+    crate::accessors!(trigger_mode, get_trigger_mode, set_trigger_mode, TriggerMode);
+
+    pub fn get_delay(&self) -> Seconds {
+        self.d.time()
+    }
+
+    pub fn set_delay(&mut self, delay: Seconds) {
+        self.d.set(self.tick, delay);
+    }
+
+    pub fn get_attack(&self) -> Seconds {
+        self.a.time()
+    }
+
+    pub fn set_attack(&mut self, attack: Seconds) {
+        self.a.set_time(self.sample_rate, attack);
+    }
+
+    pub fn get_release(&self) -> Seconds {
+        self.r.time()
+    }
+
+    pub fn set_release(&mut self, release: Seconds) {
+        self.r.set_time(self.sample_rate, release);
+    }
+
+    pub fn get_curve(&self) -> F {
+        self.a.curve()
+    }
+
+    /// Set the curve of both attack and release, as with [super::ExpAdsrSpec::set]:
+    /// strongly logarithmic at -1, linear at 0, and strongly exponential at 1.
+    pub fn set_curve(&mut self, curve: F) {
+        self.a.set_curve(curve);
+        self.r.set_curve(curve);
+    }
+
+    /// Combined delay and attack time.
     pub fn get_total(&self) -> Seconds {
-        self.d + self.a.time()
+        self.d.time() + self.a.time()
     }
 
+    /// Shorthand to set delay and attack together, split 2/3 delay and 1/3 attack.
     pub fn set_total(&mut self, total: Seconds) {
         crate::check_float_nonneg!(total);
-        self.d = total * 0.66;
-        self.a.set(self.tick, total * 0.34);
+        self.set_delay(total * 0.66);
+        self.set_attack(total * 0.34);
+    }
+
+    /// Render the env's shape into `out` without touching any live state,
+    /// holding for `gate` seconds and then releasing.
+    ///
+    /// As with [super::AdsrSpec::preview], this runs a [DaEnv] on a copy of this spec.
+    pub fn preview(&self, gate: Seconds, out: &mut [Scale]) {
+        crate::check_float_nonneg!(gate);
+        let total = gate + self.r.time();
+        let mut spec = self.clone();
+        // nothing in a DA env is tempo-synced:
+        spec.set_clock(&preview::clock(total, out.len(), clock::DEFAULT_TEMPO));
+        preview::render::<DaEnv>(&spec, preview::gate_points(gate, total, out.len()), out);
     }
 }
 
@@ -101,8 +241,8 @@ mod test {
     #[test]
     fn test_sizes() {
         use std::mem::size_of;
-        assert_eq!(size_of::<DaEnv>(), 16);
-        assert_eq!(size_of::<DaEnvSpec>(), 32);
+        assert_eq!(size_of::<DaEnv>(), 24);
+        assert_eq!(size_of::<DaEnvSpec>(), 168);
     }
     #[test]
     fn test_da() {
@@ -114,23 +254,72 @@ mod test {
         assert_eq!(env.gen(&spec), 0.0);
         assert_eq!(env.gen(&spec), 0.0);
         assert_eq!(env.gen(&spec), 0.0);
-        assert_eq!(env.gen(&spec), 0.0);
         assert_eq!(env.gen(&spec), 0.6669334400426836);
         assert_eq!(env.gen(&spec), 1.0);
         assert_eq!(env.gen(&spec), 1.0);
         env.release();
-        assert_eq!(env.gen(&spec), 1.0);
+        // no release time, so straight down to 0:
+        assert_eq!(env.gen(&spec), 0.0);
+        assert!(env.finished());
         env.hold();
+        assert!(!env.finished());
         assert_eq!(env.gen(&spec), 0.0);
 
-        // check when we're set to 0:
+        // with no delay or attack, we jump straight to 1:
         spec.set_total(0.0);
         env.hold();
-        // current implementation allows for 3 samples of 0 before jumping to 1.
-        // this is negligible, but we should fix it eventually.
-        assert_eq!(env.gen(&spec), 0.0);
-        assert_eq!(env.gen(&spec), 0.0);
-        assert_eq!(env.gen(&spec), 0.0);
         assert_eq!(env.gen(&spec), 1.0);
     }
+    #[test]
+    fn test_stages() {
+        let mut env = DaEnv::default();
+        let mut spec = DaEnvSpec::default();
+        spec.set_clock(&Clock::new(10.0));
+        spec.set_delay(0.2);
+        spec.set_attack(0.4);
+        spec.set_release(0.2);
+        assert_eq!(spec.get_total(), 0.6000000000000001);
+        env.hold();
+        let out: Vec<_> = (0..8).map(|_| env.gen(&spec)).collect();
+        assert_eq!(out, [0.0, 0.0, 0.0, 0.25, 0.5, 0.75, 1.0, 1.0]);
+        assert_eq!(env.state(), EnvState::Sustain);
+        env.release();
+        let out: Vec<_> = (0..4).map(|_| env.gen(&spec)).collect();
+        assert_eq!(out, [1.0, 0.5, 0.0, 0.0]);
+        assert!(env.finished());
+        assert_eq!(env.state(), EnvState::Finished);
+        // curved attack:
+        spec.set_curve(1.0);
+        assert_eq!(spec.get_curve(), 1.0);
+        env.hold();
+        let out: Vec<_> = (0..6).map(|_| env.gen(&spec)).collect();
+        assert_eq!(out, [0.0, 0.0, 0.0, 0.004624818578328284, 0.030638584039109372, 0.17696129529519872]);
+    }
+    #[test]
+    fn test_trigger_modes() {
+        let mut spec = DaEnvSpec::default();
+        spec.set_clock(&Clock::new(1000.0));
+        spec.set_total(0.003);
+        let mut run = |mode| {
+            let mut env = DaEnv { stage: DaStage::S, ramping: false, next_val: 0.5, timer: 0.0 };
+            spec.set_trigger_mode(mode);
+            env.trigger(&spec, false);
+            let out: Vec<_> = (0..6).map(|_| env.gen(&spec)).collect();
+            out
+        };
+        assert_eq!(run(TriggerMode::Reset), [0.0, 0.0, 0.0, 0.9803921568627451, 1.0, 1.0]);
+        // the delay holds the current level, and the attack starts from there:
+        assert_eq!(run(TriggerMode::Retrigger), [0.5, 0.5, 0.5, 1.0, 1.0, 1.0]);
+        assert_eq!(run(TriggerMode::Ramp), [0.5, 0.0, 0.0, 0.0, 0.9803921568627451, 1.0]);
+        assert_eq!(run(TriggerMode::Legato), [0.5, 0.5, 0.5, 0.5, 0.5, 0.5]);
+    }
+    #[test]
+    fn test_preview() {
+        let mut spec = DaEnvSpec::default();
+        spec.set_clock(&Clock::new(44_100.0));
+        spec.set_total(0.3);
+        let mut out = [0.0; 10];
+        spec.preview(0.5, &mut out);
+        assert_eq!(out, [0.0, 0.0, 0.0, 0.0, 0.0, 0.49019607843137253, 0.9803921568627451, 1.0, 1.0, 1.0]);
+    }
 }
@@ -0,0 +1,275 @@
+use crate::{Scale, Seconds, Gen, F};
+use crate::clock::{Clock, SetClock};
+use crate::trigger::Trigger;
+
+use super::{TimeStage, TriggerMode, EnvState, EnvStatus, HoldRelease, declick};
+
+/// Marker for which stage of a DAHDSR env we're in.
+///
+/// Used by both [Dahdsr] and [super::ExpDahdsr].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DahdsrStage {
+    #[default]
+    Delay, Attack, Hold, Decay, Sustain, Release,
+}
+
+/// Envelope generator for DAHDSR envs with linear segments.
+///
+/// During the delay, holds whatever level it was at when triggered,
+/// so retriggering a sounding env doesn't click.
+#[derive(Debug, Default)]
+pub struct Dahdsr {
+    stage: DahdsrStage,
+    next_val: Scale,
+    // progress through the delay or hold stage:
+    timer: Scale,
+    ramping: bool,
+}
+
+impl Dahdsr {
+    pub fn stage(&self) -> DahdsrStage {
+        self.stage
+    }
+
+    pub fn finished(&self) -> bool {
+        self.next_val < 0.0
+    }
+
+    fn attack_stage(&mut self, spec: &DahdsrSpec) -> Scale {
+        let queued_val = self.next_val;
+        // move on as soon as we reach the top, rather than a sample later:
+        if self.next_val >= 1.0 {
+            self.stage = DahdsrStage::Hold;
+            self.next_val = 1.0;
+            self.timer = 0.0;
+            self.hold_stage(spec)
+        } else {
+            self.next_val += spec.a.tick_over_time();
+            queued_val
+        }
+    }
+
+    fn hold_stage(&mut self, spec: &DahdsrSpec) -> Scale {
+        if self.timer < 1.0 && spec.h.time() > 0.0 {
+            self.timer += spec.h.tick_over_time();
+            self.next_val
+        } else {
+            self.stage = DahdsrStage::Decay;
+            self.decay_stage(spec)
+        }
+    }
+
+    fn decay_stage(&mut self, spec: &DahdsrSpec) -> Scale {
+        let queued_val = self.next_val;
+        if self.next_val <= spec.s {
+            self.stage = DahdsrStage::Sustain;
+            self.next_val = spec.s;
+            spec.s
+        } else {
+            self.next_val -= spec.d.tick_over_time();
+            queued_val
+        }
+    }
+}
+
+impl EnvStatus for Dahdsr {
+    fn state(&self) -> EnvState {
+        use DahdsrStage::*;
+        match self.stage {
+            Delay | Attack | Hold => EnvState::Attack,
+            Decay => EnvState::Decay,
+            Sustain => EnvState::Sustain,
+            Release if self.finished() => EnvState::Finished,
+            Release => EnvState::Release,
+        }
+    }
+}
+
+impl HoldRelease for Dahdsr {
+    fn hold(&mut self) {
+        self.stage = DahdsrStage::Delay;
+        self.timer = 0.0;
+        self.ramping = false;
+        if self.next_val < 0.0 {
+            self.next_val = 0.0;
+        }
+    }
+    fn release(&mut self) {
+        self.stage = DahdsrStage::Release;
+    }
+    fn sustain(&mut self) {
+        self.stage = DahdsrStage::Sustain;
+    }
+}
+
+impl Trigger for Dahdsr {
+    type Spec = DahdsrSpec;
+    fn trigger(&mut self, spec: &Self::Spec, from_silence: bool) {
+        let mode = spec.trigger_mode;
+        if mode.restarts(self.stage != DahdsrStage::Release, from_silence) {
+            self.hold();
+            match mode {
+                TriggerMode::Reset => self.next_val = 0.0,
+                TriggerMode::Ramp => self.ramping = self.next_val > 0.0,
+                TriggerMode::Retrigger | TriggerMode::Legato => (),
+            }
+        }
+    }
+}
+
+impl Gen<Scale> for Dahdsr {
+    type Spec = DahdsrSpec;
+    fn gen(&mut self, spec: &Self::Spec) -> Scale {
+        use DahdsrStage::*;
+        if self.ramping {
+            match declick(&mut self.next_val, spec.tick) {
+                Some(val) => return val,
+                None => self.ramping = false,
+            }
+        }
+        match self.stage {
+            Delay => {
+                if self.timer < 1.0 && spec.dl.time() > 0.0 {
+                    self.timer += spec.dl.tick_over_time();
+                    self.next_val
+                } else {
+                    self.stage = Attack;
+                    self.attack_stage(spec)
+                }
+            },
+            Attack => self.attack_stage(spec),
+            Hold => self.hold_stage(spec),
+            Decay => self.decay_stage(spec),
+            Sustain => self.next_val,
+            Release => {
+                if self.next_val < 0.0 {
+                    0.0
+                } else {
+                    let queued_val = self.next_val;
+                    self.next_val -= spec.r.tick_over_time();
+                    queued_val
+                }
+            },
+        }
+    }
+}
+
+/// Spec for [Dahdsr].
+#[derive(Debug, Default)]
+pub struct DahdsrSpec {
+    dl: TimeStage,
+    a: TimeStage,
+    h: TimeStage,
+    d: TimeStage,
+    s: Scale,
+    r: TimeStage,
+    tick: Seconds,
+    trigger_mode: TriggerMode,
+}
+
+impl SetClock for DahdsrSpec {
+    fn set_clock(&mut self, clock: &Clock) {
+        self.tick = clock.tick;
+        self.dl.set_tick(self.tick);
+        self.a.set_tick(self.tick);
+        self.h.set_tick(self.tick);
+        self.d.set_tick(self.tick);
+        self.r.set_tick(self.tick);
+    }
+}
+
+impl DahdsrSpec {
+    crate::accessors!(trigger_mode, get_trigger_mode, set_trigger_mode, TriggerMode);
+
+    pub fn get(&self, stage: DahdsrStage) -> F {
+        use DahdsrStage::*;
+        match stage {
+            Delay => self.dl.time(),
+            Attack => self.a.time(),
+            Hold => self.h.time(),
+            Decay => self.d.time(),
+            Sustain => self.s,
+            Release => self.r.time(),
+        }
+    }
+
+    pub fn set(&mut self, stage: DahdsrStage, val: F) {
+        use DahdsrStage::*;
+        match stage {
+            Delay => self.dl.set(self.tick, val),
+            Attack => self.a.set(self.tick, val),
+            Hold => self.h.set(self.tick, val),
+            Decay => self.d.set(self.tick, val),
+            Sustain => {
+                crate::check_float_01!(val);
+                self.s = val;
+            },
+            Release => self.r.set(self.tick, val),
+        };
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use DahdsrStage::*;
+    fn spec(times: [F; 6]) -> DahdsrSpec {
+        let mut spec = DahdsrSpec::default();
+        spec.set_clock(&Clock::new(10.0));
+        for (stage, val) in [Delay, Attack, Hold, Decay, Sustain, Release].into_iter().zip(times) {
+            spec.set(stage, val);
+        }
+        spec
+    }
+    #[test]
+    fn test_sizes() {
+        use std::mem::size_of;
+        assert_eq!(size_of::<Dahdsr>(), 24);
+        assert_eq!(size_of::<DahdsrSpec>(), 104);
+    }
+    #[test]
+    fn test_dahdsr() {
+        let mut env = Dahdsr::default();
+        let spec = spec([0.2, 0.2, 0.2, 0.2, 0.5, 0.2]);
+        env.hold();
+        let out: Vec<_> = (0..12).map(|_| env.gen(&spec)).collect();
+        assert_eq!(out, [0.0, 0.0, 0.0, 0.5, 1.0, 1.0, 1.0, 0.5, 0.5, 0.5, 0.5, 0.5]);
+        assert_eq!(env.stage(), Sustain);
+        env.release();
+        let out: Vec<_> = (0..4).map(|_| env.gen(&spec)).collect();
+        assert_eq!(out, [0.5, 0.0, 0.0, 0.0]);
+        assert!(env.finished());
+        // retriggering starts over from silence:
+        env.hold();
+        assert!(!env.finished());
+        assert_eq!(env.gen(&spec), 0.0);
+    }
+    #[test]
+    fn test_zero_times() {
+        let mut env = Dahdsr::default();
+        let spec = spec([0.0, 0.0, 0.0, 0.0, 0.5, 0.0]);
+        env.hold();
+        // no delay or hold, so we jump straight to the top and down to sustain:
+        assert_eq!(env.gen(&spec), 0.0);
+        assert_eq!(env.gen(&spec), 1.0);
+        assert_eq!(env.gen(&spec), 0.5);
+        assert_eq!(env.stage(), Sustain);
+    }
+    #[test]
+    fn test_retrigger() {
+        let mut env = Dahdsr::default();
+        let spec = spec([0.2, 0.2, 0.0, 0.0, 0.5, 1.0]);
+        env.hold();
+        for _ in 0..10 {
+            env.gen(&spec);
+        }
+        env.release();
+        env.gen(&spec);
+        let level = env.gen(&spec);
+        // the delay holds the current level rather than dropping to zero:
+        env.hold();
+        assert_eq!(env.gen(&spec), level - 0.1);
+        assert_eq!(env.gen(&spec), level - 0.1);
+        assert_eq!(env.stage(), Delay);
+    }
+}
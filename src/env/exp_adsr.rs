@@ -1,13 +1,19 @@
-use crate::{Scale, Hz, Gen, F};
-use crate::clock::{Clock, SetClock};
+use crate::{Scale, Seconds, Hz, Gen, F};
+use crate::clock::{Clock, SetClock, Division};
+use crate::trigger::Trigger;
 
-use super::{adsr::AdsrStage, ExpTimeStage, HoldRelease};
+use super::{adsr::AdsrStage, ExpTimeStage, EnvParam, TriggerMode, ReleaseMode, EnvLoop, LoopRate, EnvState, EnvStatus, HoldRelease, declick, preview};
 
 /// Envelope generator for ADSR envs with exponential curved segments.
 #[derive(Debug, Default)]
 pub struct ExpAdsr {
     stage: AdsrStage,
     next_val: Scale,
+    ramping: bool,
+    // progress through an EnvLoop::Ads loop:
+    cycle: F,
+    // level the release started from:
+    from: Scale,
 }
 
 impl ExpAdsr {
@@ -15,6 +21,10 @@ impl ExpAdsr {
         if self.next_val < 0.0 {
             self.next_val = 0.0;
         }
+        self.ramping = false;
+        if stage == AdsrStage::R {
+            self.from = self.next_val;
+        }
         self.stage = stage;
     }
     pub fn stage(&self) -> AdsrStage {
@@ -35,9 +45,22 @@ impl ExpAdsr {
     }
 }
 
+impl EnvStatus for ExpAdsr {
+    fn state(&self) -> EnvState {
+        match self.stage {
+            AdsrStage::A => EnvState::Attack,
+            AdsrStage::D => EnvState::Decay,
+            AdsrStage::S => EnvState::Sustain,
+            AdsrStage::R if self.finished() => EnvState::Finished,
+            AdsrStage::R => EnvState::Release,
+        }
+    }
+}
+
 impl HoldRelease for ExpAdsr {
     fn hold(&mut self) {
         self.set_stage(AdsrStage::A);
+        self.cycle = 0.0;
     }
     fn release(&mut self) {
         self.set_stage(AdsrStage::R);
@@ -47,17 +70,43 @@ impl HoldRelease for ExpAdsr {
     }
 }
 
+impl Trigger for ExpAdsr {
+    type Spec = ExpAdsrSpec;
+    fn trigger(&mut self, spec: &Self::Spec, from_silence: bool) {
+        let mode = spec.trigger_mode;
+        if mode.restarts(self.stage != AdsrStage::R, from_silence) {
+            self.hold();
+            match mode {
+                TriggerMode::Reset => self.next_val = 0.0,
+                TriggerMode::Ramp => self.ramping = self.next_val > 0.0,
+                TriggerMode::Retrigger | TriggerMode::Legato => (),
+            }
+        }
+    }
+}
+
 impl Gen<Scale> for ExpAdsr {
     type Spec = ExpAdsrSpec;
     fn gen(&mut self, spec: &Self::Spec) -> Scale {
         use AdsrStage::*;
+        if self.ramping {
+            match declick(&mut self.next_val, spec.sample_rate.recip()) {
+                Some(val) => return val,
+                None => self.ramping = false,
+            }
+        }
+        if spec.looping == EnvLoop::Ads && self.stage != R && spec.loop_rate.advance(&mut self.cycle) {
+            self.stage = A;
+        }
         match self.stage {
             S => self.next_val,
             R => {
                 if self.next_val < 0.0 {
                     0.0
                 } else {
-                    self.compute_current(&spec.r)
+                    let current_val = self.next_val;
+                    self.next_val = spec.r.base() * spec.release_mode.scale(self.from) + self.next_val * spec.r.coef();
+                    current_val
                 }
             },
             A => {
@@ -77,7 +126,7 @@ impl Gen<Scale> for ExpAdsr {
                 if self.next_val > spec.s {
                     self.compute_current(&spec.d)
                 } else {
-                    self.transition(S, spec.s)
+                    self.transition(spec.looping.after_decay(), spec.s)
                 }
             },
         }
@@ -86,16 +135,19 @@ impl Gen<Scale> for ExpAdsr {
 
 /// Spec for [ExpAdsr].
 ///
-/// Hardcodes sensible curves under the hood
-/// based on a subjective opinion of what sounds good.
-/// We could make those constants modifiable in the future if necessary.
-#[derive(Debug)]
+/// Defaults to sensible curves based on a subjective opinion of what sounds good;
+/// set a stage's [EnvParam::Curve] to change it.
+#[derive(Debug, Clone)]
 pub struct ExpAdsrSpec {
     a: ExpTimeStage,
     d: ExpTimeStage,
     s: Scale,
     r: ExpTimeStage,
     sample_rate: Hz,
+    trigger_mode: TriggerMode,
+    release_mode: ReleaseMode,
+    looping: EnvLoop,
+    loop_rate: LoopRate,
 }
 
 impl Default for ExpAdsrSpec {
@@ -106,6 +158,10 @@ impl Default for ExpAdsrSpec {
             s: 1.0,
             r: ExpTimeStage::falling(0.0),
             sample_rate: 0.0,
+            trigger_mode: TriggerMode::default(),
+            release_mode: ReleaseMode::default(),
+            looping: EnvLoop::default(),
+            loop_rate: LoopRate::default(),
         }
     }
 }
@@ -116,33 +172,81 @@ impl SetClock for ExpAdsrSpec {
         self.a.set_sample_rate(self.sample_rate);
         self.d.set_sample_rate(self.sample_rate);
         self.r.set_sample_rate(self.sample_rate);
+        self.loop_rate.set_clock(clock);
     }
 }
 
 impl ExpAdsrSpec {
-    pub fn get(&self, stage: AdsrStage) -> F {
+    crate::accessors!(trigger_mode, get_trigger_mode, set_trigger_mode, TriggerMode);
+    crate::accessors!(release_mode, get_release_mode, set_release_mode, ReleaseMode);
+    crate::accessors!(looping, get_loop, set_loop, EnvLoop);
+
+    pub fn get_loop_rate(&self) -> Hz {
+        self.loop_rate.rate()
+    }
+
+    /// Set how often an [EnvLoop::Ads] loop restarts, in Hz.
+    pub fn set_loop_rate(&mut self, rate: Hz) {
+        self.loop_rate.set_rate(rate);
+    }
+
+    pub fn get_loop_division(&self) -> Option<Division> {
+        self.loop_rate.division()
+    }
+
+    /// Sync the loop rate to the host tempo, or go back to the free rate in Hz with `None`.
+    pub fn set_loop_division(&mut self, division: Option<Division>) {
+        self.loop_rate.set_division(division);
+    }
+
+    /// Get a stage's time or level, or its curve; sustain has no curve, so reads as 0.
+    pub fn get(&self, param: impl Into<EnvParam<AdsrStage>>) -> F {
         use AdsrStage::*;
-        match stage {
-            A => self.a.time(),
-            D => self.d.time(),
-            S => self.s,
-            R => self.r.time(),
+        match param.into() {
+            EnvParam::Stage(A) => self.a.time(),
+            EnvParam::Stage(D) => self.d.time(),
+            EnvParam::Stage(S) => self.s,
+            EnvParam::Stage(R) => self.r.time(),
+            EnvParam::Curve(A) => self.a.curve(),
+            EnvParam::Curve(D) => self.d.curve(),
+            EnvParam::Curve(S) => 0.0,
+            EnvParam::Curve(R) => self.r.curve(),
         }
     }
 
-    pub fn set(&mut self, stage: AdsrStage, val: F) {
+    /// Set a stage's time or level, or its curve: strongly logarithmic at -1,
+    /// linear at 0, and strongly exponential at 1. Setting the sustain curve does nothing.
+    ///
+    /// Changing the curve never changes the stage's time.
+    pub fn set(&mut self, param: impl Into<EnvParam<AdsrStage>>, val: F) {
         use AdsrStage::*;
-        match stage {
-            A => self.a.set_time(self.sample_rate, val),
-            D => self.d.set_time(self.sample_rate, val),
-            S => {
+        match param.into() {
+            EnvParam::Stage(A) => self.a.set_time(self.sample_rate, val),
+            EnvParam::Stage(D) => self.d.set_time(self.sample_rate, val),
+            EnvParam::Stage(S) => {
                 crate::check_float_01!(val);
                 self.s = val;
                 self.d.set_peak(self.s);
             },
-            R => self.r.set_time(self.sample_rate, val),
+            EnvParam::Stage(R) => self.r.set_time(self.sample_rate, val),
+            EnvParam::Curve(A) => self.a.set_curve(val),
+            EnvParam::Curve(D) => self.d.set_curve(val),
+            EnvParam::Curve(S) => (),
+            EnvParam::Curve(R) => self.r.set_curve(val),
         };
     }
+
+    /// Render the env's shape into `out` without touching any live state,
+    /// holding for `gate` seconds and then releasing.
+    ///
+    /// As with [super::AdsrSpec::preview], this runs an [ExpAdsr] on a copy of this spec.
+    pub fn preview(&self, gate: Seconds, out: &mut [Scale]) {
+        crate::check_float_nonneg!(gate);
+        let total = gate + self.r.time();
+        let mut spec = self.clone();
+        spec.set_clock(&preview::clock(total, out.len(), self.loop_rate.tempo()));
+        preview::render::<ExpAdsr>(&spec, preview::gate_points(gate, total, out.len()), out);
+    }
 }
 
 #[cfg(test)]
@@ -161,8 +265,8 @@ mod test {
     #[test]
     fn test_size() {
         use std::mem::size_of;
-        assert_eq!(size_of::<ExpAdsr>(), 16);
-        assert_eq!(size_of::<ExpAdsrSpec>(), 160);
+        assert_eq!(size_of::<ExpAdsr>(), 32);
+        assert_eq!(size_of::<ExpAdsrSpec>(), 256);
     }
     #[test]
     fn a_reaches_1() {
@@ -177,7 +281,7 @@ mod test {
     }
     #[test]
     fn d_reaches_s() {
-        let mut adsr = ExpAdsr { stage: AdsrStage::D, next_val: 1.0 };
+        let mut adsr = ExpAdsr { stage: AdsrStage::D, next_val: 1.0, ramping: false, cycle: 0.0, from: 0.0 };
         let spec = spec(0.0, 1.0, 0.5, 0.0);
         let mut output = adsr.gen(&spec);
         assert_eq!(output, 1.0);
@@ -188,7 +292,7 @@ mod test {
     }
     #[test]
     fn r_reaches_0() {
-        let mut adsr = ExpAdsr { stage: AdsrStage::R, next_val: 0.5 };
+        let mut adsr = ExpAdsr { stage: AdsrStage::R, next_val: 0.5, ramping: false, cycle: 0.0, from: 0.5 };
         let spec = spec(0.0, 0.0, 0.5, 1.0);
         let mut output = adsr.gen(&spec);
         assert!(output > 0.0);
@@ -197,4 +301,80 @@ mod test {
         }
         assert!(output == 0.0);
     }
+    #[test]
+    fn test_release_modes() {
+        let mut spec = spec(0.0, 0.0, 1.0, 0.1);
+        spec.set_clock(&Clock::new(1000.0));
+        let mut release = |mode, from| {
+            let mut adsr = ExpAdsr { stage: AdsrStage::S, next_val: from, ramping: false, cycle: 0.0, from: 0.0 };
+            spec.set_release_mode(mode);
+            adsr.release();
+            let len = (0..1000).take_while(|_| adsr.gen(&spec) > 0.0).count();
+            assert!(adsr.finished());
+            len
+        };
+        assert_eq!(release(ReleaseMode::Slope, 1.0), 100);
+        assert_eq!(release(ReleaseMode::Slope, 0.2), 69);
+        // the full time from anywhere, though still finishing from silence:
+        assert_eq!(release(ReleaseMode::Time, 1.0), 100);
+        assert_eq!(release(ReleaseMode::Time, 0.2), 100);
+        assert_eq!(release(ReleaseMode::Time, 0.0), 0);
+    }
+    #[test]
+    fn test_preview() {
+        let spec = spec(0.1, 0.1, 0.5, 0.2);
+        let mut out = [0.0; 12];
+        spec.preview(0.4, &mut out);
+        assert_eq!(out, [0.0, 0.7007153551199833, 1.0, 1.0, 0.5354438937036725, 0.5, 0.5, 0.5, 0.5, 0.13976647901521186, 0.035443893703672444, 0.005232374863528209]);
+    }
+    #[test]
+    fn test_curve_durations() {
+        use AdsrStage::*;
+        // every full-range stage should take its time, give or take a sample of rounding,
+        // at every curve setting:
+        for curve in [-1.0, -0.6, -0.2, 0.0, 0.2, 0.6, 1.0] {
+            let mut spec = spec(0.1, 0.1, 0.0, 0.1);
+            spec.set_clock(&Clock::new(1000.0));
+            for stage in [A, D, R] {
+                spec.set(EnvParam::Curve(stage), curve);
+                assert_eq!(spec.get(EnvParam::Curve(stage)), curve);
+            }
+            assert_eq!(spec.get(A), 0.1);
+
+            let mut adsr = ExpAdsr::default();
+            adsr.hold();
+            let attack = (0..1000).take_while(|_| adsr.gen(&spec) < 1.0).count();
+            assert!(attack.abs_diff(100) <= 1, "attack took {} at curve {}", attack, curve);
+            let decay = (0..1000).take_while(|_| adsr.gen(&spec) > 0.0).count();
+            assert!(decay.abs_diff(100) <= 1, "decay took {} at curve {}", decay, curve);
+
+            spec.set(S, 1.0);
+            let mut adsr = ExpAdsr { stage: R, next_val: 1.0, ramping: false, cycle: 0.0, from: 1.0 };
+            let release = (0..1000).take_while(|_| adsr.gen(&spec) > 0.0).count();
+            assert!(release.abs_diff(100) <= 1, "release took {} at curve {}", release, curve);
+        }
+    }
+    #[test]
+    fn test_curve_shapes() {
+        use AdsrStage::*;
+        let mut spec = spec(0.1, 0.0, 1.0, 0.0);
+        spec.set_clock(&Clock::new(1000.0));
+        // about halfway through the attack:
+        let mid = |spec: &ExpAdsrSpec| {
+            let mut adsr = ExpAdsr::default();
+            adsr.hold();
+            (0..50).map(|_| adsr.gen(spec)).last().unwrap()
+        };
+        // defaults bow upwards:
+        assert_eq!(spec.get(EnvParam::Curve(A)), -0.530288344574257);
+        assert_eq!(mid(&spec), 0.6917508750869007);
+        // linear:
+        spec.set(EnvParam::Curve(A), 0.0);
+        assert_eq!(mid(&spec), 0.49000000000000027);
+        // fully exponential bows well down, fully logarithmic further up:
+        spec.set(EnvParam::Curve(A), 1.0);
+        assert_eq!(mid(&spec), 0.02852654950619028);
+        spec.set(EnvParam::Curve(A), -1.0);
+        assert_eq!(mid(&spec), 0.9670983075658729);
+    }
 }
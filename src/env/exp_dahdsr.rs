@@ -0,0 +1,314 @@
+use crate::{Scale, Seconds, Hz, Gen, F};
+use crate::clock::{Clock, SetClock};
+use crate::trigger::Trigger;
+
+use super::{dahdsr::DahdsrStage, TimeStage, ExpTimeStage, EnvParam, TriggerMode, EnvState, EnvStatus, HoldRelease, declick};
+
+/// Envelope generator for DAHDSR envs with exponential curved segments.
+///
+/// As with [super::Dahdsr], the delay holds whatever level the env was at when triggered.
+#[derive(Debug, Default)]
+pub struct ExpDahdsr {
+    stage: DahdsrStage,
+    next_val: Scale,
+    // progress through the delay or hold stage:
+    timer: Scale,
+    ramping: bool,
+}
+
+impl ExpDahdsr {
+    pub fn set_stage(&mut self, stage: DahdsrStage) {
+        if self.next_val < 0.0 {
+            self.next_val = 0.0;
+        }
+        if stage == DahdsrStage::Delay || stage == DahdsrStage::Hold {
+            self.timer = 0.0;
+        }
+        self.ramping = false;
+        self.stage = stage;
+    }
+    pub fn stage(&self) -> DahdsrStage {
+        self.stage
+    }
+    pub fn finished(&self) -> bool {
+        self.next_val < 0.0
+    }
+    fn compute_current(&mut self, stage: &ExpTimeStage) -> Scale {
+        let current_val = self.next_val;
+        self.next_val = stage.base() + self.next_val * stage.coef();
+        current_val
+    }
+    // count through a delay or hold stage, returning false once it's over:
+    fn count(&mut self, stage: &TimeStage) -> bool {
+        if self.timer < 1.0 && stage.time() > 0.0 {
+            self.timer += stage.tick_over_time();
+            true
+        } else {
+            false
+        }
+    }
+    fn attack_stage(&mut self, spec: &ExpDahdsrSpec) -> Scale {
+        if self.next_val < 1.0 {
+            self.compute_current(&spec.a)
+        } else {
+            self.set_stage(DahdsrStage::Hold);
+            self.next_val = 1.0;
+            self.hold_stage(spec)
+        }
+    }
+    fn hold_stage(&mut self, spec: &ExpDahdsrSpec) -> Scale {
+        if self.count(&spec.h) {
+            self.next_val
+        } else {
+            self.stage = DahdsrStage::Decay;
+            self.decay_stage(spec)
+        }
+    }
+    fn decay_stage(&mut self, spec: &ExpDahdsrSpec) -> Scale {
+        if self.next_val > spec.s {
+            self.compute_current(&spec.d)
+        } else {
+            self.stage = DahdsrStage::Sustain;
+            self.next_val = spec.s;
+            spec.s
+        }
+    }
+}
+
+impl EnvStatus for ExpDahdsr {
+    fn state(&self) -> EnvState {
+        use DahdsrStage::*;
+        match self.stage {
+            Delay | Attack | Hold => EnvState::Attack,
+            Decay => EnvState::Decay,
+            Sustain => EnvState::Sustain,
+            Release if self.finished() => EnvState::Finished,
+            Release => EnvState::Release,
+        }
+    }
+}
+
+impl HoldRelease for ExpDahdsr {
+    fn hold(&mut self) {
+        self.set_stage(DahdsrStage::Delay);
+    }
+    fn release(&mut self) {
+        self.set_stage(DahdsrStage::Release);
+    }
+    fn sustain(&mut self) {
+        self.set_stage(DahdsrStage::Sustain);
+    }
+}
+
+impl Trigger for ExpDahdsr {
+    type Spec = ExpDahdsrSpec;
+    fn trigger(&mut self, spec: &Self::Spec, from_silence: bool) {
+        let mode = spec.trigger_mode;
+        if mode.restarts(self.stage != DahdsrStage::Release, from_silence) {
+            self.hold();
+            match mode {
+                TriggerMode::Reset => self.next_val = 0.0,
+                TriggerMode::Ramp => self.ramping = self.next_val > 0.0,
+                TriggerMode::Retrigger | TriggerMode::Legato => (),
+            }
+        }
+    }
+}
+
+impl Gen<Scale> for ExpDahdsr {
+    type Spec = ExpDahdsrSpec;
+    fn gen(&mut self, spec: &Self::Spec) -> Scale {
+        use DahdsrStage::*;
+        if self.ramping {
+            match declick(&mut self.next_val, spec.tick) {
+                Some(val) => return val,
+                None => self.ramping = false,
+            }
+        }
+        match self.stage {
+            Delay => {
+                if self.count(&spec.dl) {
+                    self.next_val
+                } else {
+                    self.stage = Attack;
+                    self.attack_stage(spec)
+                }
+            },
+            Attack => self.attack_stage(spec),
+            Hold => self.hold_stage(spec),
+            Decay => self.decay_stage(spec),
+            Sustain => self.next_val,
+            Release => {
+                if self.next_val < 0.0 {
+                    0.0
+                } else {
+                    self.compute_current(&spec.r)
+                }
+            },
+        }
+    }
+}
+
+impl From<DahdsrStage> for EnvParam<DahdsrStage> {
+    fn from(stage: DahdsrStage) -> Self {
+        Self::Stage(stage)
+    }
+}
+
+/// Spec for [ExpDahdsr].
+///
+/// Uses the same default curves as [super::ExpAdsrSpec];
+/// set the attack, decay or release [EnvParam::Curve] to change them.
+#[derive(Debug, Clone)]
+pub struct ExpDahdsrSpec {
+    dl: TimeStage,
+    a: ExpTimeStage,
+    h: TimeStage,
+    d: ExpTimeStage,
+    s: Scale,
+    r: ExpTimeStage,
+    tick: Seconds,
+    sample_rate: Hz,
+    trigger_mode: TriggerMode,
+}
+
+impl Default for ExpDahdsrSpec {
+    fn default() -> Self {
+        Self {
+            dl: TimeStage::default(),
+            a: ExpTimeStage::rising(1.0),
+            h: TimeStage::default(),
+            d: ExpTimeStage::falling(1.0),
+            s: 1.0,
+            r: ExpTimeStage::falling(0.0),
+            tick: 0.0,
+            sample_rate: 0.0,
+            trigger_mode: TriggerMode::default(),
+        }
+    }
+}
+
+impl SetClock for ExpDahdsrSpec {
+    fn set_clock(&mut self, clock: &Clock) {
+        self.tick = clock.tick;
+        self.sample_rate = clock.sample_rate;
+        self.dl.set_tick(self.tick);
+        self.a.set_sample_rate(self.sample_rate);
+        self.h.set_tick(self.tick);
+        self.d.set_sample_rate(self.sample_rate);
+        self.r.set_sample_rate(self.sample_rate);
+    }
+}
+
+impl ExpDahdsrSpec {
+    crate::accessors!(trigger_mode, get_trigger_mode, set_trigger_mode, TriggerMode);
+
+    /// Get a stage's time or level, or its curve; only attack, decay and release have curves,
+    /// so the others read as 0.
+    pub fn get(&self, param: impl Into<EnvParam<DahdsrStage>>) -> F {
+        use DahdsrStage::*;
+        match param.into() {
+            EnvParam::Stage(Delay) => self.dl.time(),
+            EnvParam::Stage(Attack) => self.a.time(),
+            EnvParam::Stage(Hold) => self.h.time(),
+            EnvParam::Stage(Decay) => self.d.time(),
+            EnvParam::Stage(Sustain) => self.s,
+            EnvParam::Stage(Release) => self.r.time(),
+            EnvParam::Curve(Attack) => self.a.curve(),
+            EnvParam::Curve(Decay) => self.d.curve(),
+            EnvParam::Curve(Release) => self.r.curve(),
+            EnvParam::Curve(Delay | Hold | Sustain) => 0.0,
+        }
+    }
+
+    /// Set a stage's time or level, or its curve, as with [super::ExpAdsrSpec::set].
+    /// Setting the delay, hold or sustain curve does nothing.
+    pub fn set(&mut self, param: impl Into<EnvParam<DahdsrStage>>, val: F) {
+        use DahdsrStage::*;
+        match param.into() {
+            EnvParam::Stage(Delay) => self.dl.set(self.tick, val),
+            EnvParam::Stage(Attack) => self.a.set_time(self.sample_rate, val),
+            EnvParam::Stage(Hold) => self.h.set(self.tick, val),
+            EnvParam::Stage(Decay) => self.d.set_time(self.sample_rate, val),
+            EnvParam::Stage(Sustain) => {
+                crate::check_float_01!(val);
+                self.s = val;
+                self.d.set_peak(self.s);
+            },
+            EnvParam::Stage(Release) => self.r.set_time(self.sample_rate, val),
+            EnvParam::Curve(Attack) => self.a.set_curve(val),
+            EnvParam::Curve(Decay) => self.d.set_curve(val),
+            EnvParam::Curve(Release) => self.r.set_curve(val),
+            EnvParam::Curve(Delay | Hold | Sustain) => (),
+        };
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use DahdsrStage::*;
+    fn spec(times: [F; 6]) -> ExpDahdsrSpec {
+        let mut spec = ExpDahdsrSpec::default();
+        spec.set_clock(&Clock::new(1000.0));
+        for (stage, val) in [Delay, Attack, Hold, Decay, Sustain, Release].into_iter().zip(times) {
+            spec.set(stage, val);
+        }
+        spec
+    }
+    // count samples until the env leaves the given stage:
+    fn stage_len(env: &mut ExpDahdsr, spec: &ExpDahdsrSpec, stage: DahdsrStage) -> usize {
+        let mut len = 0;
+        while env.stage() == stage && len < 10_000 {
+            env.gen(spec);
+            len += 1;
+        }
+        len
+    }
+    #[test]
+    fn test_sizes() {
+        use std::mem::size_of;
+        assert_eq!(size_of::<ExpDahdsr>(), 24);
+        assert_eq!(size_of::<ExpDahdsrSpec>(), 256);
+    }
+    #[test]
+    fn test_stage_times() {
+        let mut env = ExpDahdsr::default();
+        let spec = spec([0.05, 0.1, 0.02, 0.1, 0.0, 0.1]);
+        env.hold();
+        // the sample that leaves each stage is the first of the next one,
+        // so only the delay's count runs over:
+        assert_eq!(stage_len(&mut env, &spec, Delay), 51);
+        assert_eq!(stage_len(&mut env, &spec, Attack), 100);
+        assert_eq!(stage_len(&mut env, &spec, Hold), 20);
+        assert_eq!(stage_len(&mut env, &spec, Decay), 100);
+        assert_eq!(env.stage(), Sustain);
+        assert_eq!(env.gen(&spec), 0.0);
+        assert!(!env.finished());
+    }
+    #[test]
+    fn test_release() {
+        let spec = spec([0.0, 0.0, 0.0, 0.0, 1.0, 0.1]);
+        let mut env = ExpDahdsr { stage: Release, next_val: 1.0, timer: 0.0, ramping: false };
+        let release = (0..10_000).take_while(|_| env.gen(&spec) > 0.0).count();
+        assert_eq!(release, 100);
+        assert!(env.finished());
+        assert_eq!(env.gen(&spec), 0.0);
+        // retriggering starts over from silence:
+        env.hold();
+        assert!(!env.finished());
+        assert_eq!(env.gen(&spec), 0.0);
+    }
+    #[test]
+    fn test_curves() {
+        let mut spec = spec([0.0, 0.1, 0.0, 0.1, 0.5, 0.1]);
+        assert_eq!(spec.get(EnvParam::Curve(Attack)), -0.530288344574257);
+        assert_eq!(spec.get(EnvParam::Curve(Hold)), 0.0);
+        spec.set(EnvParam::Curve(Attack), 0.0);
+        let mut env = ExpDahdsr::default();
+        env.hold();
+        // linear attack:
+        let out: Vec<_> = (0..4).map(|_| env.gen(&spec)).collect();
+        assert_eq!(out, [0.0, 0.01, 0.02, 0.03]);
+    }
+}
@@ -0,0 +1,105 @@
+use crate::{Hz, Seconds, Bpm, F};
+use crate::clock::{self, Clock, SetClock, Division};
+
+use super::AdsrStage;
+
+/// Loop mode for [super::Adsr] and [super::ExpAdsr], turning them into shaped modulators while held.
+///
+/// Releasing always stops the loop and runs the release stage as usual.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EnvLoop {
+    /// Plain ADSR, no looping.
+    #[default]
+    Off,
+    /// Once the decay reaches the sustain level, go straight back into the attack,
+    /// cycling between sustain and peak at a speed set by the attack and decay times.
+    Ad,
+    /// Restart the attack at the spec's loop rate, holding at the sustain level in between.
+    /// With no loop rate, this holds at sustain like [EnvLoop::Off].
+    Ads,
+}
+
+impl EnvLoop {
+    /// Stage to go to once the decay reaches the sustain level.
+    pub(super) fn after_decay(self) -> AdsrStage {
+        match self {
+            EnvLoop::Ad => AdsrStage::A,
+            EnvLoop::Off | EnvLoop::Ads => AdsrStage::S,
+        }
+    }
+}
+
+/// Loop rate for an ADSR spec, in Hz or synced to the host tempo.
+#[derive(Debug, Clone)]
+pub(super) struct LoopRate {
+    tick: Seconds,
+    tempo: Bpm,
+    rate: Hz,
+    division: Option<Division>,
+    /// cached progress per sample through each loop
+    inc: F,
+}
+
+impl Default for LoopRate {
+    fn default() -> Self {
+        Self {
+            tick: 0.0,
+            tempo: clock::DEFAULT_TEMPO,
+            rate: 0.0,
+            division: None,
+            inc: 0.0,
+        }
+    }
+}
+
+impl SetClock for LoopRate {
+    fn set_clock(&mut self, clock: &Clock) {
+        self.tick = clock.tick;
+        self.tempo = clock.tempo;
+        self.update_inc();
+    }
+}
+
+impl LoopRate {
+    pub(super) fn tempo(&self) -> Bpm {
+        self.tempo
+    }
+
+    pub(super) fn rate(&self) -> Hz {
+        self.rate
+    }
+
+    pub(super) fn set_rate(&mut self, rate: Hz) {
+        crate::check_float_nonneg!(rate);
+        self.rate = rate;
+        self.update_inc();
+    }
+
+    pub(super) fn division(&self) -> Option<Division> {
+        self.division
+    }
+
+    pub(super) fn set_division(&mut self, division: Option<Division>) {
+        self.division = division;
+        self.update_inc();
+    }
+
+    /// Move a loop's progress on by a sample, returning true each time it comes round.
+    pub(super) fn advance(&self, cycle: &mut F) -> bool {
+        *cycle += self.inc;
+        if *cycle >= 1.0 {
+            *cycle -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn update_inc(&mut self) {
+        let rate = match self.division {
+            Some(division) => clock::beats_to_hz(self.tempo, division.beats()),
+            None => self.rate,
+        };
+        self.inc = rate * self.tick;
+    }
+}
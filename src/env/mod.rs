@@ -1,5 +1,7 @@
 //! Envelope generator implementations.
 
+use crate::{Scale, Seconds, Gen, F};
+
 /// Linear ADSR envelope generator.
 mod adsr;
 pub use self::adsr::{Adsr, AdsrSpec, AdsrStage};
@@ -8,14 +10,118 @@ pub use self::adsr::{Adsr, AdsrSpec, AdsrStage};
 mod exp_adsr;
 pub use self::exp_adsr::{ExpAdsr, ExpAdsrSpec};
 
+/// Linear DAHDSR envelope generator.
+mod dahdsr;
+pub use self::dahdsr::{Dahdsr, DahdsrSpec, DahdsrStage};
+
+/// Exponential DAHDSR envelope generator.
+mod exp_dahdsr;
+pub use self::exp_dahdsr::{ExpDahdsr, ExpDahdsrSpec};
+
 /// Linear DA envelope generator.
 mod da;
-pub use self::da::{DaEnv, DaEnvSpec};
+pub use self::da::{DaEnv, DaEnvSpec, DaStage};
+
+/// Joystick-style envelope generator for X/Y positions.
+mod vector;
+pub use self::vector::{VectorEnv, VectorEnvSpec};
+
+/// Multi-segment envelope generator with curved breakpoints.
+mod mseg;
+pub use self::mseg::{Mseg, MsegSpec, Breakpoint};
+
+/// Key and velocity tracking for ADSR envs.
+mod tracked;
+pub use self::tracked::{Tracked, TrackedSpec, AdsrEnv};
+
+/// Helpers for rendering env shapes from a spec alone.
+mod preview;
 
 /// Utility structs for setting env times.
 mod time_stage;
 use self::time_stage::{TimeStage, ExpTimeStage};
 
+/// Loop modes for ADSR envs.
+mod looping;
+pub use self::looping::EnvLoop;
+use self::looping::LoopRate;
+
+/// A settable parameter of an env spec: either a stage's value
+/// (its time, or level for sustain), or a stage's curve.
+///
+/// Stages convert straight into [EnvParam::Stage], so `spec.set(stage, val)` works as before.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EnvParam<S> {
+    Stage(S),
+    Curve(S),
+}
+
+impl From<AdsrStage> for EnvParam<AdsrStage> {
+    fn from(stage: AdsrStage) -> Self {
+        Self::Stage(stage)
+    }
+}
+
+/// What an env does when triggered (see [crate::trigger::Trigger]) while it's still sounding.
+///
+/// Set on each env's spec; calling [HoldRelease::hold] directly bypasses it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TriggerMode {
+    /// Jump to zero and start over.
+    Reset,
+    /// Start over from the current level.
+    #[default]
+    Retrigger,
+    /// Carry on as before if still held, only starting over after a release or from silence.
+    Legato,
+    /// Ramp quickly down to zero, then start over, avoiding the click of [TriggerMode::Reset].
+    Ramp,
+}
+
+impl TriggerMode {
+    /// Whether a trigger should start the env over, given whether it's still held.
+    fn restarts(self, held: bool, from_silence: bool) -> bool {
+        from_silence || !held || self != TriggerMode::Legato
+    }
+}
+
+/// How long an env's release takes, depending on the level it starts from.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ReleaseMode {
+    /// Fall at a fixed rate: the release time is how long a release from full level takes,
+    /// so releasing from lower down finishes sooner.
+    #[default]
+    Slope,
+    /// Always take the release time, from whatever level the release starts at.
+    Time,
+}
+
+impl ReleaseMode {
+    /// How much to scale a full-range release by, when releasing from `from`.
+    fn scale(self, from: Scale) -> Scale {
+        match self {
+            ReleaseMode::Slope => 1.0,
+            // never quite zero, so a release from silence still finishes:
+            ReleaseMode::Time => from.max(F::EPSILON),
+        }
+    }
+}
+
+// time taken by a TriggerMode::Ramp from full level down to zero:
+const DECLICK_TIME: Seconds = 0.002;
+
+// step a TriggerMode::Ramp down towards zero, returning None once it's there:
+fn declick(level: &mut Scale, tick: Seconds) -> Option<Scale> {
+    if *level > 0.0 {
+        let out = *level;
+        *level -= tick / DECLICK_TIME;
+        Some(out)
+    } else {
+        *level = 0.0;
+        None
+    }
+}
+
 /// Trait for Env generators to receive state-change messages.
 // TODO these can just be a single fn with a stage arg, I think.
 pub trait HoldRelease {
@@ -23,3 +129,62 @@ pub trait HoldRelease {
     fn release(&mut self);
     fn sustain(&mut self);
 }
+
+/// Coarse state of an env, the same for every kind of env, so voice managers can treat them alike.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EnvState {
+    /// Anything before the decay: delay, attack or hold
+    /// (or, for an [Mseg] or [VectorEnv], any held segment).
+    Attack,
+    Decay,
+    /// Holding at the sustain level until released
+    /// (or, for a [VectorEnv], frozen or at the end of its path).
+    Sustain,
+    Release,
+    /// Released all the way down to silence (or, for a [VectorEnv], to the end of its path).
+    Finished,
+}
+
+impl EnvState {
+    /// Event to report on entering this state, if any.
+    fn event(self) -> Option<EnvEvent> {
+        match self {
+            EnvState::Decay => Some(EnvEvent::Decay),
+            EnvState::Sustain => Some(EnvEvent::Sustain),
+            EnvState::Finished => Some(EnvEvent::Finished),
+            EnvState::Attack | EnvState::Release => None,
+        }
+    }
+}
+
+/// Stage change reported by [EnvStatus::gen_event].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EnvEvent {
+    /// Entered the decay stage.
+    Decay,
+    /// Entered the sustain stage.
+    Sustain,
+    /// Finished releasing, so the voice can be freed.
+    Finished,
+}
+
+/// Trait for envs to report their state, e.g. to a voice allocator.
+pub trait EnvStatus {
+    fn state(&self) -> EnvState;
+
+    fn finished(&self) -> bool {
+        self.state() == EnvState::Finished
+    }
+
+    /// Generate the next value as with [Gen::gen], along with an event if the env changed stage.
+    fn gen_event<O>(&mut self, spec: &<Self as Gen<O>>::Spec) -> (O, Option<EnvEvent>)
+    where
+        Self: Gen<O>,
+    {
+        let before = self.state();
+        let val = self.gen(spec);
+        let after = self.state();
+        let event = if after != before { after.event() } else { None };
+        (val, event)
+    }
+}
@@ -0,0 +1,398 @@
+use crate::{Scale, Seconds, Bpm, Gen, F};
+use crate::clock::{self, Clock, SetClock};
+use crate::trigger::Trigger;
+
+use super::{TriggerMode, EnvState, EnvStatus, HoldRelease, declick};
+
+// how far a curve of +/-1 bends its segment:
+const CURVE_STRENGTH: F = 8.0;
+
+/// One point of an [MsegSpec].
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Breakpoint {
+    /// Time taken to get here from the previous point; in seconds, or in beats if synced.
+    pub time: F,
+    /// Level at this point, 0..=1.
+    pub level: Scale,
+    /// Curve of the segment leading here, -1..=1: negative bows upwards, positive downwards,
+    /// and zero is linear.
+    pub curve: F,
+}
+
+impl Breakpoint {
+    pub const fn new(time: F, level: Scale, curve: F) -> Self {
+        Self { time, level, curve }
+    }
+}
+
+/// Multi-segment envelope generator, moving through up to `N` breakpoints of an [MsegSpec].
+///
+/// Starts from the level it was at when triggered. While held, stops at the spec's sustain point,
+/// or repeats its loop; once released, runs on through the remaining points.
+#[derive(Debug, Default)]
+pub struct Mseg<const N: usize> {
+    // index of the point we're heading to:
+    seg: usize,
+    progress: Scale,
+    from: Scale,
+    level: Scale,
+    gated: bool,
+    holding: bool,
+    ramping: bool,
+    // passed the last point:
+    done: bool,
+}
+
+impl<const N: usize> Mseg<N> {
+    /// Index of the point the env is heading towards.
+    pub fn segment(&self) -> usize {
+        self.seg
+    }
+
+    /// True once the env has passed its last point.
+    pub fn finished(&self) -> bool {
+        self.done
+    }
+}
+
+impl<const N: usize> EnvStatus for Mseg<N> {
+    fn state(&self) -> EnvState {
+        if self.done {
+            EnvState::Finished
+        } else if self.holding {
+            EnvState::Sustain
+        } else if self.gated {
+            EnvState::Attack
+        } else {
+            EnvState::Release
+        }
+    }
+}
+
+impl<const N: usize> HoldRelease for Mseg<N> {
+    fn hold(&mut self) {
+        self.seg = 0;
+        self.progress = 0.0;
+        self.from = self.level;
+        self.gated = true;
+        self.holding = false;
+        self.ramping = false;
+        self.done = false;
+    }
+    fn release(&mut self) {
+        self.gated = false;
+        self.holding = false;
+    }
+    fn sustain(&mut self) {
+        self.holding = true;
+    }
+}
+
+impl<const N: usize> Trigger for Mseg<N> {
+    type Spec = MsegSpec<N>;
+    fn trigger(&mut self, spec: &Self::Spec, from_silence: bool) {
+        let mode = spec.trigger_mode;
+        if mode.restarts(self.gated, from_silence) {
+            if mode == TriggerMode::Reset {
+                self.level = 0.0;
+            }
+            self.hold();
+            if mode == TriggerMode::Ramp {
+                self.from = 0.0;
+                self.ramping = self.level > 0.0;
+            }
+        }
+    }
+}
+
+impl<const N: usize> Gen<Scale> for Mseg<N> {
+    type Spec = MsegSpec<N>;
+    fn gen(&mut self, spec: &Self::Spec) -> Scale {
+        if self.ramping {
+            match declick(&mut self.level, spec.tick) {
+                Some(level) => return level,
+                None => self.ramping = false,
+            }
+        }
+        if self.seg >= spec.len {
+            self.done = true;
+        }
+        if self.holding || self.done {
+            return self.level;
+        }
+        let to = spec.points[self.seg];
+        let out = self.from + (to.level - self.from) * shape(self.progress, to.curve, to.level < self.from);
+        self.progress += spec.incs[self.seg];
+        if self.progress >= 1.0 {
+            // we've reached the point, so work out where to go next:
+            self.from = to.level;
+            self.level = to.level;
+            self.progress = 0.0;
+            self.holding = self.gated && spec.sustain == Some(self.seg);
+            self.seg = match spec.loop_points {
+                Some((start, end)) if self.gated && !self.holding && end == self.seg => start,
+                _ => self.seg + 1,
+            };
+            self.done = self.seg >= spec.len;
+        } else {
+            self.level = out;
+        }
+        out
+    }
+}
+
+// bend a segment's progress according to its curve; falling segments are mirrored,
+// so a positive curve always bows downwards:
+fn shape(progress: Scale, curve: F, falling: bool) -> Scale {
+    if curve == 0.0 {
+        return progress;
+    }
+    let bend = |t: Scale| ((CURVE_STRENGTH * curve * t).exp() - 1.0) / ((CURVE_STRENGTH * curve).exp() - 1.0);
+    if falling {
+        1.0 - bend(1.0 - progress)
+    } else {
+        bend(progress)
+    }
+}
+
+/// Spec for [Mseg]: up to `N` breakpoints, with an optional sustain point and loop.
+#[derive(Debug, Clone)]
+pub struct MsegSpec<const N: usize> {
+    points: [Breakpoint; N],
+    len: usize,
+    sustain: Option<usize>,
+    loop_points: Option<(usize, usize)>,
+    sync: bool,
+    tick: Seconds,
+    tempo: Bpm,
+    trigger_mode: TriggerMode,
+    /// cached progress per sample of each segment
+    incs: [Scale; N],
+}
+
+impl<const N: usize> Default for MsegSpec<N> {
+    fn default() -> Self {
+        Self {
+            points: [Breakpoint::default(); N],
+            len: 0,
+            sustain: None,
+            loop_points: None,
+            sync: false,
+            tick: 0.0,
+            tempo: clock::DEFAULT_TEMPO,
+            trigger_mode: TriggerMode::default(),
+            incs: [1.0; N],
+        }
+    }
+}
+
+impl<const N: usize> SetClock for MsegSpec<N> {
+    fn set_clock(&mut self, clock: &Clock) {
+        self.tick = clock.tick;
+        self.tempo = clock.tempo;
+        self.update_incs();
+    }
+}
+
+impl<const N: usize> MsegSpec<N> {
+    crate::accessors!(trigger_mode, get_trigger_mode, set_trigger_mode, TriggerMode);
+
+    /// Number of breakpoints in use.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Set how many breakpoints are in use, up to `N`.
+    pub fn set_len(&mut self, len: usize) {
+        crate::check_int_less_than!(len, N + 1);
+        self.len = len;
+    }
+
+    pub fn get_point(&self, index: usize) -> Breakpoint {
+        self.points[index]
+    }
+
+    pub fn set_point(&mut self, index: usize, point: Breakpoint) {
+        crate::check_float_nonneg!(point.time);
+        crate::check_float_01!(point.level);
+        crate::check_float_bipolar!(point.curve);
+        self.points[index] = point;
+        self.incs[index] = self.inc(point.time);
+    }
+
+    pub fn get_sustain(&self) -> Option<usize> {
+        self.sustain
+    }
+
+    /// Hold at the given point until released.
+    pub fn set_sustain(&mut self, sustain: Option<usize>) {
+        if let Some(point) = sustain {
+            crate::check_int_less_than!(point, N);
+        }
+        self.sustain = sustain;
+    }
+
+    pub fn get_loop(&self) -> Option<(usize, usize)> {
+        self.loop_points
+    }
+
+    /// Once at the loop's end point, head back towards its start point, until released;
+    /// the segment leading to the start point sets how long the jump back takes.
+    pub fn set_loop(&mut self, loop_points: Option<(usize, usize)>) {
+        if let Some((start, end)) = loop_points {
+            crate::check_int_less_than!(start, end);
+            crate::check_int_less_than!(end, N);
+        }
+        self.loop_points = loop_points;
+    }
+
+    pub fn get_sync(&self) -> bool {
+        self.sync
+    }
+
+    /// Measure segment times in beats at the host tempo, rather than in seconds.
+    pub fn set_sync(&mut self, sync: bool) {
+        self.sync = sync;
+        self.update_incs();
+    }
+
+    fn inc(&self, time: F) -> Scale {
+        let seconds = if self.sync { 1.0 / clock::beats_to_hz(self.tempo, time) } else { time };
+        if seconds > 0.0 {
+            self.tick / seconds
+        } else {
+            // as with TimeStage, jump straight past the point:
+            1.0
+        }
+    }
+
+    fn update_incs(&mut self) {
+        for i in 0..N {
+            self.incs[i] = self.inc(self.points[i].time);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    // up to 1 at 0.2s, down to 0.5 at 0.4s, and back to 0 at 0.6s:
+    fn spec() -> MsegSpec<4> {
+        let mut spec = MsegSpec::<4>::default();
+        spec.set_clock(&Clock::new(10.0));
+        spec.set_len(3);
+        spec.set_point(0, Breakpoint::new(0.2, 1.0, 0.0));
+        spec.set_point(1, Breakpoint::new(0.2, 0.5, 0.0));
+        spec.set_point(2, Breakpoint::new(0.2, 0.0, 0.0));
+        spec
+    }
+    fn run(env: &mut Mseg<4>, spec: &MsegSpec<4>, n: usize) -> Vec<Scale> {
+        (0..n).map(|_| env.gen(spec)).collect()
+    }
+    #[test]
+    fn test_sizes() {
+        use std::mem::size_of;
+        assert_eq!(size_of::<Mseg<4>>(), 40);
+        assert_eq!(size_of::<MsegSpec<4>>(), 200);
+    }
+    #[test]
+    fn test_mseg() {
+        let mut env = Mseg::<4>::default();
+        let spec = spec();
+        env.hold();
+        assert_eq!(run(&mut env, &spec, 8), [0.0, 0.5, 1.0, 0.75, 0.5, 0.25, 0.0, 0.0]);
+        assert!(env.finished());
+    }
+    #[test]
+    fn test_curves() {
+        let mut spec = spec();
+        let mid = |spec: &MsegSpec<4>| {
+            let mut env = Mseg::<4>::default();
+            env.hold();
+            run(&mut env, spec, 2)[1]
+        };
+        spec.set_point(0, Breakpoint::new(0.2, 1.0, 1.0));
+        assert_eq!(mid(&spec), 0.017986209962091555);
+        spec.set_point(0, Breakpoint::new(0.2, 1.0, -1.0));
+        assert_eq!(mid(&spec), 0.9820137900379085);
+        // falling segments bow the same way:
+        assert_eq!(shape(0.5, 1.0, true), 1.0 - shape(0.5, 1.0, false));
+    }
+    #[test]
+    fn test_sustain() {
+        let mut env = Mseg::<4>::default();
+        let mut spec = spec();
+        spec.set_sustain(Some(1));
+        env.hold();
+        assert_eq!(run(&mut env, &spec, 8), [0.0, 0.5, 1.0, 0.75, 0.5, 0.5, 0.5, 0.5]);
+        assert!(!env.finished());
+        env.release();
+        assert_eq!(run(&mut env, &spec, 4), [0.5, 0.25, 0.0, 0.0]);
+        assert!(env.finished());
+        // retriggering starts from the current level:
+        spec.set_point(0, Breakpoint::new(0.0, 1.0, 0.0));
+        env.hold();
+        assert_eq!(run(&mut env, &spec, 2), [0.0, 1.0]);
+    }
+    #[test]
+    fn test_loop() {
+        let mut env = Mseg::<4>::default();
+        let mut spec = spec();
+        spec.set_loop(Some((0, 1)));
+        env.hold();
+        // up to the top, then bouncing between 1 and 0.5:
+        assert_eq!(run(&mut env, &spec, 10), [0.0, 0.5, 1.0, 0.75, 0.5, 0.75, 1.0, 0.75, 0.5, 0.75]);
+        // releasing carries on to the end:
+        env.release();
+        assert_eq!(run(&mut env, &spec, 6), [1.0, 0.75, 0.5, 0.25, 0.0, 0.0]);
+    }
+    #[test]
+    fn test_trigger_modes() {
+        let mut spec = spec();
+        spec.set_sustain(Some(1));
+        spec.set_clock(&Clock::new(1000.0));
+        let mut env = Mseg::<4>::default();
+        env.trigger(&spec, true);
+        for _ in 0..1000 {
+            env.gen(&spec);
+        }
+        // held at the sustain point, so legato carries on:
+        spec.set_trigger_mode(TriggerMode::Legato);
+        env.trigger(&spec, false);
+        assert_eq!(env.gen(&spec), 0.5);
+        // ramping drops to zero, then starts over:
+        spec.set_trigger_mode(TriggerMode::Ramp);
+        env.trigger(&spec, false);
+        assert_eq!(run(&mut env, &spec, 4), [0.5, 0.0, 0.005, 0.01]);
+    }
+    #[test]
+    fn test_events() {
+        use crate::env::EnvEvent;
+        let mut env = Mseg::<4>::default();
+        let mut spec = spec();
+        spec.set_sustain(Some(1));
+        env.hold();
+        let events: Vec<_> = (0..6).map(|_| env.gen_event(&spec).1).collect();
+        assert_eq!(events, [None, None, None, Some(EnvEvent::Sustain), None, None]);
+        env.release();
+        let events: Vec<_> = (0..3).map(|_| env.gen_event(&spec).1).collect();
+        assert_eq!(events, [None, Some(EnvEvent::Finished), None]);
+    }
+    #[test]
+    fn test_sync() {
+        let mut env = Mseg::<4>::default();
+        let mut spec = spec();
+        let mut clock = Clock::new(10.0);
+        clock.set_tempo(300.0);
+        spec.set_clock(&clock);
+        spec.set_sync(true);
+        // a beat at 300bpm is 0.2s, so 1 beat takes 2 samples and 0.2 beats less than 1:
+        spec.set_point(0, Breakpoint::new(1.0, 1.0, 0.0));
+        env.hold();
+        assert_eq!(run(&mut env, &spec, 5), [0.0, 0.5, 1.0, 0.5, 0.0]);
+    }
+}
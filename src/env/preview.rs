@@ -0,0 +1,43 @@
+use crate::{Scale, Seconds, Bpm, Gen};
+use crate::clock::Clock;
+
+use super::HoldRelease;
+
+/// Clock that fits `points` samples into `total` seconds, so a preview spec
+/// can run an env one output point per sample.
+///
+/// Pass the spec's own tempo, so tempo-synced parts line up as they would live.
+pub(super) fn clock(total: Seconds, points: usize, tempo: Bpm) -> Clock {
+    let mut clock = if total > 0.0 && points > 0 {
+        Clock::new(points as Seconds / total)
+    } else {
+        // nothing to spread out, so any rate will do:
+        Clock::new(1.0)
+    };
+    clock.set_tempo(tempo);
+    clock
+}
+
+/// Run a fresh env through `out`, holding until `gate` points in, then releasing.
+pub(super) fn render<E>(spec: &E::Spec, gate: usize, out: &mut [Scale])
+where
+    E: Gen<Scale> + HoldRelease + Default,
+{
+    let mut env = E::default();
+    env.hold();
+    for (i, point) in out.iter_mut().enumerate() {
+        if i == gate {
+            env.release();
+        }
+        *point = env.gen(spec);
+    }
+}
+
+/// Number of points out of `len` that fall within the gate.
+pub(super) fn gate_points(gate: Seconds, total: Seconds, len: usize) -> usize {
+    if total > 0.0 {
+        (gate / total * len as Seconds).round() as usize
+    } else {
+        len
+    }
+}
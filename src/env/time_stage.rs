@@ -4,7 +4,7 @@ use crate::{Seconds, Scale, F, Hz};
 ///
 /// Stores both the time in seconds, and tick over time (1/time * 1/rate) to avoid
 /// calculating it at processing time.
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
 pub struct TimeStage {
     time: Seconds,
     tick_over_time: Scale,
@@ -40,21 +40,33 @@ const OFFSET_RISE: F = 0.22313016014;
 // const OFFSET_FALL: f64 = (-4.95f64).exp();
 const OFFSET_FALL: F = 0.00708340892;
 
+// range of offsets reachable through curve settings, from strongest to gentlest:
+const OFFSET_MIN: F = 0.001;
+const OFFSET_MAX: F = 100.0;
+
 /// Represents one of the time stages of an *exponential* env generator (e.g. A, D, or R).
 ///
 /// Stores the time in seconds, as well as cached values required to iteratively compute
 /// an exponential curve.
 ///
+/// Each step computes `base + val * coef`, which moves `val` exponentially towards or away from
+/// an anchor `offset` beyond one end of the stage. The curve setting chooses the offset and end:
+/// negative curves bow upwards (logarithmic), positive curves bow downwards (exponential),
+/// and zero is exactly linear. Either way, a full-range stage takes exactly `time`.
+///
 /// NB no default here.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct ExpTimeStage {
     time: Seconds,
 
+    curve: F,
     offset: F,
     peak: Scale,
     // TODO cd store as i8? or bool?
     dir: F,
 
+    /// length of the stage in samples
+    rate: F,
     base: F,
     coef: F,
 }
@@ -70,26 +82,52 @@ impl ExpTimeStage {
     }
 
     fn new(time: Seconds, sample_rate: Hz, offset: F, peak: Scale, dir: F) -> Self {
-        let mut val = Self { time, offset, peak, dir, base: 0.0, coef: 0.0, };
+        // approaching the peak bows upwards when rising and downwards when falling:
+        let curve = -dir * offset_to_curve(offset);
+        let mut val = Self { time, curve, offset, peak, dir, rate: 0.0, base: 0.0, coef: 0.0, };
         val.compute(time * sample_rate);
         val
     }
 
     fn compute(&mut self, rate: F) {
-        self.compute_coef(rate);
+        self.rate = rate;
+        self.compute_coef();
         self.compute_base();
     }
 
-    fn compute_coef(&mut self, rate: F) {
+    // does the curve move away from the start, rather than towards the peak?
+    fn departs(&self) -> bool {
+        self.curve * self.dir > 0.0
+    }
+
+    fn compute_coef(&mut self) {
+        let rate = self.rate;
         if rate > 0.0 {
-            self.coef = ((-((1.0 + self.offset) / self.offset).ln()) / rate).exp()
+            if self.curve == 0.0 {
+                self.coef = 1.0;
+            } else {
+                // hit the far end of a full-range stage after exactly `rate` samples:
+                let exp = ((1.0 + self.offset) / self.offset).ln() / rate;
+                self.coef = if self.departs() { exp.exp() } else { (-exp).exp() };
+            }
         } else {
             self.coef = 0.0;
         }
     }
 
     fn compute_base(&mut self) {
-        self.base = (self.peak + self.dir * self.offset) * (1.0 - self.coef);
+        if self.rate > 0.0 && self.curve == 0.0 {
+            // linear, so base is just the step:
+            self.base = self.dir / self.rate;
+        } else {
+            // at time 0, coef is 0.0, so we jump straight past the peak:
+            let anchor = if self.rate > 0.0 && self.departs() {
+                self.peak - self.dir * (1.0 + self.offset)
+            } else {
+                self.peak + self.dir * self.offset
+            };
+            self.base = anchor * (1.0 - self.coef);
+        }
     }
 
     pub fn time(&self) -> Seconds {
@@ -119,4 +157,29 @@ impl ExpTimeStage {
         self.peak = peak;
         self.compute_base();
     }
+
+    pub fn curve(&self) -> F {
+        self.curve
+    }
+
+    /// Set the curve, from strongly logarithmic at -1, through linear at 0,
+    /// to strongly exponential at 1.
+    pub fn set_curve(&mut self, curve: F) {
+        crate::check_float_bipolar!(curve);
+        self.curve = curve;
+        if curve != 0.0 {
+            self.offset = curve_to_offset(curve.abs());
+        }
+        self.compute_coef();
+        self.compute_base();
+    }
+}
+
+// spread curve strengths evenly over orders of magnitude of offset:
+fn curve_to_offset(strength: F) -> F {
+    OFFSET_MIN * (OFFSET_MAX / OFFSET_MIN).powf(1.0 - strength)
+}
+
+fn offset_to_curve(offset: F) -> F {
+    1.0 - (offset / OFFSET_MIN).ln() / (OFFSET_MAX / OFFSET_MIN).ln()
 }
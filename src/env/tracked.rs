@@ -0,0 +1,268 @@
+use crate::{Scale, Note, Gen, F};
+use crate::clock::{Clock, SetClock};
+use crate::trigger::{NoteOn, Trigger};
+use crate::util::lirp;
+
+use super::{Adsr, ExpAdsr, AdsrStage, EnvState, EnvStatus, HoldRelease};
+
+// how far full velocity tracking speeds up (or slows down) the attack, either side of middle velocity:
+const VEL_ATTACK_RANGE: F = 4.0;
+
+/// Trait for ADSR-style envs that can report their stage, so [Tracked] knows which times to scale.
+pub trait AdsrEnv: Gen<Scale> + HoldRelease {
+    fn stage(&self) -> AdsrStage;
+}
+
+impl AdsrEnv for Adsr {
+    fn stage(&self) -> AdsrStage {
+        self.stage()
+    }
+}
+
+impl AdsrEnv for ExpAdsr {
+    fn stage(&self) -> AdsrStage {
+        self.stage()
+    }
+}
+
+/// Wraps an ADSR env, scaling its times by key and its attack and level by velocity.
+///
+/// Rather than changing the times in the env's spec, this runs the env faster or slower
+/// and interpolates between its outputs, so one spec can be shared by every voice.
+/// Call [Tracked::track] with each note before holding;
+/// until then, it runs just like the plain env.
+#[derive(Debug)]
+pub struct Tracked<E> {
+    env: E,
+    // last two outputs of the env, and how far we are between them:
+    prev: Scale,
+    next: Scale,
+    frac: Scale,
+    primed: bool,
+    // env steps per sample, from the current note:
+    key_rate: F,
+    attack_rate: F,
+    gain: Scale,
+}
+
+impl<E: Default> Default for Tracked<E> {
+    fn default() -> Self {
+        Self {
+            env: E::default(),
+            prev: 0.0,
+            next: 0.0,
+            frac: 0.0,
+            primed: false,
+            key_rate: 1.0,
+            attack_rate: 1.0,
+            gain: 1.0,
+        }
+    }
+}
+
+impl<E> Tracked<E> {
+    crate::getters!(env, get_env, get_env_mut, E);
+
+    /// Set up the scaling for a new note.
+    pub fn track<S>(&mut self, spec: &TrackedSpec<S>, note: &NoteOn) {
+        let vel = note.vel as F / 127.0;
+        self.key_rate = ((note.note as F - spec.center as F) * spec.key / 12.0).exp2();
+        self.attack_rate = VEL_ATTACK_RANGE.powf(spec.vel_attack * (vel * 2.0 - 1.0));
+        self.gain = 1.0 - spec.vel_level * (1.0 - vel);
+    }
+}
+
+impl<E: HoldRelease> HoldRelease for Tracked<E> {
+    fn hold(&mut self) {
+        self.env.hold();
+        self.primed = false;
+    }
+    fn release(&mut self) {
+        // start interpolating afresh, so the release isn't held up by the last pre-release output:
+        self.env.release();
+        self.primed = false;
+    }
+    fn sustain(&mut self) {
+        self.env.sustain();
+    }
+}
+
+impl<E: Trigger> Trigger for Tracked<E> {
+    type Spec = TrackedSpec<E::Spec>;
+    fn trigger(&mut self, spec: &Self::Spec, from_silence: bool) {
+        self.env.trigger(&spec.env, from_silence);
+        self.primed = false;
+    }
+}
+
+impl<E: AdsrEnv> Gen<Scale> for Tracked<E> {
+    type Spec = TrackedSpec<E::Spec>;
+    fn gen(&mut self, spec: &Self::Spec) -> Scale {
+        if !self.primed {
+            self.prev = self.env.gen(&spec.env);
+            self.next = self.env.gen(&spec.env);
+            self.frac = 0.0;
+            self.primed = true;
+        }
+        let out = lirp(self.prev, self.next, self.frac) * self.gain;
+        self.frac += match self.env.stage() {
+            AdsrStage::A => self.key_rate * self.attack_rate,
+            AdsrStage::D | AdsrStage::R => self.key_rate,
+            AdsrStage::S => 1.0,
+        };
+        while self.frac >= 1.0 {
+            self.prev = self.next;
+            self.next = self.env.gen(&spec.env);
+            self.frac -= 1.0;
+        }
+        out
+    }
+}
+
+impl<E: AdsrEnv + EnvStatus> EnvStatus for Tracked<E> {
+    fn state(&self) -> EnvState {
+        self.env.state()
+    }
+}
+
+/// Spec for [Tracked]: a shared env spec, plus how much the note changes it.
+#[derive(Debug)]
+pub struct TrackedSpec<S> {
+    env: S,
+    key: F,
+    center: Note,
+    vel_level: Scale,
+    vel_attack: F,
+}
+
+impl<S: Default> Default for TrackedSpec<S> {
+    fn default() -> Self {
+        Self {
+            env: S::default(),
+            key: 0.0,
+            center: 60,
+            vel_level: 0.0,
+            vel_attack: 0.0,
+        }
+    }
+}
+
+impl<S: SetClock> SetClock for TrackedSpec<S> {
+    fn set_clock(&mut self, clock: &Clock) {
+        self.env.set_clock(clock);
+    }
+}
+
+impl<S> TrackedSpec<S> {
+    crate::getters!(env, get_env, get_env_mut, S);
+    crate::accessors!(center, get_center, set_center, Note);
+
+    pub fn get_key(&self) -> F {
+        self.key
+    }
+
+    /// Set key tracking of attack, decay and release times, -1..=1: at 1, times halve
+    /// with every octave above the center note, and double with every octave below.
+    /// Negative values work the other way around.
+    pub fn set_key(&mut self, key: F) {
+        crate::check_float_bipolar!(key);
+        self.key = key;
+    }
+
+    pub fn get_vel_level(&self) -> Scale {
+        self.vel_level
+    }
+
+    /// Set how much velocity scales the env's level: at 0 every note peaks at 1,
+    /// and at 1 the peak is the note's velocity.
+    pub fn set_vel_level(&mut self, vel_level: Scale) {
+        crate::check_float_01!(vel_level);
+        self.vel_level = vel_level;
+    }
+
+    pub fn get_vel_attack(&self) -> F {
+        self.vel_attack
+    }
+
+    /// Set velocity tracking of attack time, -1..=1: at 1, full velocity makes the attack
+    /// four times quicker and zero velocity four times slower. Negative values work the other way around.
+    pub fn set_vel_attack(&mut self, vel_attack: F) {
+        crate::check_float_bipolar!(vel_attack);
+        self.vel_attack = vel_attack;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::env::{AdsrSpec, ExpAdsrSpec};
+    use super::*;
+    fn spec() -> TrackedSpec<AdsrSpec> {
+        use AdsrStage::*;
+        let mut spec = TrackedSpec::<AdsrSpec>::default();
+        spec.set_clock(&Clock::new(10.0));
+        spec.get_env_mut().set(A, 0.4);
+        spec.get_env_mut().set(D, 0.4);
+        spec.get_env_mut().set(S, 0.5);
+        spec.get_env_mut().set(R, 0.4);
+        spec
+    }
+    fn run(env: &mut Tracked<Adsr>, spec: &TrackedSpec<AdsrSpec>, n: usize) -> Vec<Scale> {
+        (0..n).map(|_| env.gen(spec)).collect()
+    }
+    #[test]
+    fn test_sizes() {
+        use std::mem::size_of;
+        assert_eq!(size_of::<Tracked<ExpAdsr>>(), 88);
+        assert_eq!(size_of::<TrackedSpec<ExpAdsrSpec>>(), 288);
+    }
+    #[test]
+    fn test_untracked() {
+        let spec = spec();
+        let mut env = Tracked::<Adsr>::default();
+        env.track(&spec, &NoteOn { note: 72, vel: 0 });
+        env.hold();
+        // no tracking, so just the plain env:
+        assert_eq!(run(&mut env, &spec, 7), [0.0, 0.25, 0.5, 0.75, 1.0, 1.0, 0.75]);
+    }
+    #[test]
+    fn test_no_track() {
+        let spec = spec();
+        let mut env = Tracked::<Adsr>::default();
+        // holding without tracking a note gives the plain env too:
+        env.hold();
+        assert_eq!(run(&mut env, &spec, 7), [0.0, 0.25, 0.5, 0.75, 1.0, 1.0, 0.75]);
+    }
+    #[test]
+    fn test_key() {
+        let mut spec = spec();
+        spec.set_key(1.0);
+        let mut env = Tracked::<Adsr>::default();
+        // an octave up, so twice as fast:
+        env.track(&spec, &NoteOn { note: 72, vel: 127 });
+        env.hold();
+        assert_eq!(run(&mut env, &spec, 5), [0.0, 0.5, 1.0, 0.75, 0.5]);
+        env.release();
+        assert_eq!(run(&mut env, &spec, 3), [0.5, 0.0, 0.0]);
+        // an octave down, so half as fast:
+        let mut env = Tracked::<Adsr>::default();
+        env.track(&spec, &NoteOn { note: 48, vel: 127 });
+        env.hold();
+        assert_eq!(run(&mut env, &spec, 5), [0.0, 0.125, 0.25, 0.375, 0.5]);
+    }
+    #[test]
+    fn test_vel() {
+        let mut spec = spec();
+        spec.set_vel_level(1.0);
+        spec.set_vel_attack(1.0);
+        let mut env = Tracked::<Adsr>::default();
+        env.track(&spec, &NoteOn { note: 60, vel: 127 });
+        env.hold();
+        // full velocity: full level, and a quicker attack:
+        assert_eq!(run(&mut env, &spec, 4), [0.0, 1.0, 1.0, 0.75]);
+        let mut env = Tracked::<Adsr>::default();
+        env.track(&spec, &NoteOn { note: 60, vel: 0 });
+        env.hold();
+        // zero velocity, so silent:
+        assert_eq!(run(&mut env, &spec, 4), [0.0, 0.0, 0.0, 0.0]);
+    }
+}
@@ -0,0 +1,330 @@
+use crate::{Scale, Seconds, Gen};
+use crate::clock::{Clock, SetClock};
+use crate::trigger::Trigger;
+use crate::util::lirp;
+
+use super::{TimeStage, TriggerMode, EnvState, EnvStatus, HoldRelease, declick};
+
+/// Joystick-style envelope generator: traces a path through a series of `N` (x, y) points,
+/// e.g. to drive a [crate::osc::VectorOsc] around its plane.
+///
+/// Moves linearly from one point to the next, optionally looping back
+/// to an earlier point once it reaches the last one.
+#[derive(Debug, Default)]
+pub struct VectorEnv<const N: usize> {
+    // index of the point we're moving away from:
+    seg: usize,
+    progress: Scale,
+    // last output, so a trigger can start over from there:
+    pos: (Scale, Scale),
+    // where the first segment starts, if not at the first point; also the start of a ramp:
+    start: Option<(Scale, Scale)>,
+    // progress of a TriggerMode::Ramp back to the first point, from 1 down to 0:
+    ramp: Scale,
+    releasing: bool,
+    frozen: bool,
+    ramping: bool,
+    // reached the end of the path:
+    ended: bool,
+}
+
+/// A path has no sustain stage to jump to, so here [HoldRelease::sustain] means
+/// "stop where you are": the env freezes at its current position until released or held again.
+impl<const N: usize> HoldRelease for VectorEnv<N> {
+    fn hold(&mut self) {
+        self.seg = 0;
+        self.progress = 0.0;
+        self.start = None;
+        self.releasing = false;
+        self.frozen = false;
+        self.ramping = false;
+        self.ended = false;
+    }
+    // stop looping, so the path runs out to its last point:
+    fn release(&mut self) {
+        self.releasing = true;
+        self.frozen = false;
+    }
+    // unlike other envs, this freezes rather than jumping ahead (see above):
+    fn sustain(&mut self) {
+        self.frozen = true;
+    }
+}
+
+impl<const N: usize> EnvStatus for VectorEnv<N> {
+    fn state(&self) -> EnvState {
+        match (self.releasing, self.ended) {
+            (true, true) => EnvState::Finished,
+            (true, false) => EnvState::Release,
+            (false, true) => EnvState::Sustain,
+            (false, false) if self.frozen && !self.ramping => EnvState::Sustain,
+            (false, false) => EnvState::Attack,
+        }
+    }
+}
+
+impl<const N: usize> Trigger for VectorEnv<N> {
+    type Spec = VectorEnvSpec<N>;
+    fn trigger(&mut self, spec: &Self::Spec, from_silence: bool) {
+        let mode = spec.trigger_mode;
+        if mode.restarts(!self.releasing, from_silence) {
+            let pos = self.pos;
+            self.hold();
+            match mode {
+                // start the path over, heading from wherever we are to the second point:
+                TriggerMode::Retrigger => self.start = Some(pos),
+                TriggerMode::Ramp => {
+                    self.start = Some(pos);
+                    self.ramp = 1.0;
+                    self.ramping = true;
+                },
+                TriggerMode::Reset | TriggerMode::Legato => (),
+            }
+        }
+    }
+}
+
+impl<const N: usize> VectorEnv<N> {
+    fn next(&self, spec: &VectorEnvSpec<N>) -> Option<usize> {
+        if self.seg + 1 < N {
+            Some(self.seg + 1)
+        } else if self.releasing {
+            None
+        } else {
+            spec.loop_start
+        }
+    }
+}
+
+impl<const N: usize> Gen<(Scale, Scale)> for VectorEnv<N> {
+    type Spec = VectorEnvSpec<N>;
+    fn gen(&mut self, spec: &Self::Spec) -> (Scale, Scale) {
+        if self.ramping {
+            let first = spec.points[0];
+            let start = self.start.unwrap_or(first);
+            match declick(&mut self.ramp, spec.tick) {
+                Some(ramp) => {
+                    self.pos = (lirp(first.0, start.0, ramp), lirp(first.1, start.1, ramp));
+                    return self.pos;
+                },
+                None => {
+                    self.ramping = false;
+                    self.start = None;
+                },
+            }
+        }
+        let from = match self.start {
+            Some(start) if self.seg == 0 => start,
+            _ => spec.points[self.seg],
+        };
+        let Some(next) = self.next(spec) else {
+            // reached the end of the path:
+            self.ended = true;
+            self.pos = from;
+            return from;
+        };
+        let to = spec.points[next];
+        self.pos = (lirp(from.0, to.0, self.progress), lirp(from.1, to.1, self.progress));
+        if !self.frozen {
+            self.progress += spec.times[self.seg].tick_over_time();
+            if self.progress >= 1.0 {
+                // carry any leftover progress into the next segment:
+                self.progress = (self.progress - 1.0).min(1.0);
+                self.seg = next;
+                // only the first time through starts anywhere but the first point:
+                self.start = None;
+            }
+        }
+        self.pos
+    }
+}
+
+/// Spec for [VectorEnv], with a path of `N` points.
+///
+/// `times[i]` is the time taken to move from point `i` to point `i + 1`;
+/// the last time is only used when looping, to move from the last point back to the loop start.
+#[derive(Debug)]
+pub struct VectorEnvSpec<const N: usize> {
+    points: [(Scale, Scale); N],
+    times: [TimeStage; N],
+    loop_start: Option<usize>,
+    tick: Seconds,
+    trigger_mode: TriggerMode,
+}
+
+impl<const N: usize> Default for VectorEnvSpec<N> {
+    fn default() -> Self {
+        Self {
+            points: [(0.0, 0.0); N],
+            times: core::array::from_fn(|_| TimeStage::default()),
+            loop_start: None,
+            tick: 0.0,
+            trigger_mode: TriggerMode::default(),
+        }
+    }
+}
+
+impl<const N: usize> SetClock for VectorEnvSpec<N> {
+    fn set_clock(&mut self, clock: &Clock) {
+        self.tick = clock.tick;
+        for time in self.times.iter_mut() {
+            time.set_tick(self.tick);
+        }
+    }
+}
+
+impl<const N: usize> VectorEnvSpec<N> {
+    crate::accessors!(trigger_mode, get_trigger_mode, set_trigger_mode, TriggerMode);
+
+    pub fn get_point(&self, index: usize) -> (Scale, Scale) {
+        self.points[index]
+    }
+
+    /// Set a point on the path, with each axis in 0..=1.
+    pub fn set_point(&mut self, index: usize, (x, y): (Scale, Scale)) {
+        crate::check_float_01!(x);
+        crate::check_float_01!(y);
+        self.points[index] = (x, y);
+    }
+
+    pub fn get_time(&self, index: usize) -> Seconds {
+        self.times[index].time()
+    }
+
+    pub fn set_time(&mut self, index: usize, time: Seconds) {
+        crate::check_float_nonneg!(time);
+        self.times[index].set(self.tick, time);
+    }
+
+    pub fn get_loop(&self) -> Option<usize> {
+        self.loop_start
+    }
+
+    /// Loop back to the given point after the last one, or play the path once with `None`.
+    pub fn set_loop(&mut self, loop_start: Option<usize>) {
+        if let Some(start) = loop_start {
+            crate::check_int_less_than!(start, N);
+        }
+        self.loop_start = loop_start;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::env::EnvEvent;
+    use super::*;
+    fn spec() -> VectorEnvSpec<3> {
+        let mut spec = VectorEnvSpec::<3>::default();
+        spec.set_clock(&Clock::new(10.0));
+        spec.set_point(1, (1.0, 0.0));
+        spec.set_point(2, (1.0, 1.0));
+        spec.set_time(0, 0.2);
+        spec.set_time(1, 0.4);
+        spec.set_time(2, 0.2);
+        spec
+    }
+    #[test]
+    fn test_sizes() {
+        use std::mem::size_of;
+        assert_eq!(size_of::<VectorEnv<4>>(), 72);
+        assert_eq!(size_of::<VectorEnvSpec<4>>(), 160);
+    }
+    #[test]
+    fn test_path() {
+        let mut env = VectorEnv::<3>::default();
+        let spec = spec();
+        let out: Vec<_> = (0..8).map(|_| env.gen(&spec)).collect();
+        // held at the end of the path with no loop, so sustaining:
+        assert_eq!(env.state(), EnvState::Sustain);
+        assert_eq!(out, [
+            (0.0, 0.0), (0.5, 0.0),
+            (1.0, 0.0), (1.0, 0.25), (1.0, 0.5), (1.0, 0.75),
+            (1.0, 1.0), (1.0, 1.0),
+        ]);
+    }
+    #[test]
+    fn test_loop() {
+        let mut env = VectorEnv::<3>::default();
+        let mut spec = spec();
+        spec.set_loop(Some(1));
+        for _ in 0..6 {
+            env.gen(&spec);
+        }
+        // back from the last point to the loop start...
+        assert_eq!(env.gen(&spec), (1.0, 1.0));
+        assert_eq!(env.gen(&spec), (1.0, 0.5));
+        assert_eq!(env.gen(&spec), (1.0, 0.0));
+        assert_eq!(env.gen(&spec), (1.0, 0.25));
+        // ...until released, when we run out to the end:
+        env.release();
+        assert_eq!(env.state(), EnvState::Release);
+        assert_eq!(env.gen_event(&spec), ((1.0, 0.5), None));
+        assert_eq!(env.gen_event(&spec), ((1.0, 0.75), None));
+        assert_eq!(env.gen_event(&spec), ((1.0, 1.0), Some(EnvEvent::Finished)));
+        assert_eq!(env.gen(&spec), (1.0, 1.0));
+        assert!(env.finished());
+        // hold starts over:
+        env.hold();
+        assert_eq!(env.gen(&spec), (0.0, 0.0));
+    }
+    #[test]
+    fn test_sustain() {
+        let mut env = VectorEnv::<3>::default();
+        let spec = spec();
+        env.gen(&spec);
+        assert_eq!(env.state(), EnvState::Attack);
+        env.sustain();
+        assert_eq!(env.state(), EnvState::Sustain);
+        assert_eq!(env.gen(&spec), (0.5, 0.0));
+        assert_eq!(env.gen(&spec), (0.5, 0.0));
+        env.release();
+        assert_eq!(env.gen(&spec), (0.5, 0.0));
+        assert_eq!(env.gen(&spec), (1.0, 0.0));
+    }
+    #[test]
+    fn test_trigger_modes() {
+        let mut spec = spec();
+        // as in spec(), but fast enough for the declick ramp to show:
+        spec.set_clock(&Clock::new(1000.0));
+        spec.set_time(0, 0.002);
+        spec.set_time(1, 0.004);
+        let mut run = |mode| {
+            let mut env = VectorEnv::<3>::default();
+            for _ in 0..4 {
+                env.gen(&spec);
+            }
+            spec.set_trigger_mode(mode);
+            env.trigger(&spec, false);
+            let out: Vec<_> = (0..4).map(|_| env.gen(&spec)).collect();
+            out
+        };
+        // triggered at (1.0, 0.25), partway from the second point to the third:
+        assert_eq!(run(TriggerMode::Reset), [(0.0, 0.0), (0.5, 0.0), (1.0, 0.0), (1.0, 0.25)]);
+        // heading from there back to the second point:
+        assert_eq!(run(TriggerMode::Retrigger), [(1.0, 0.25), (1.0, 0.125), (1.0, 0.0), (1.0, 0.25)]);
+        // gliding back to the first point before starting over:
+        assert_eq!(run(TriggerMode::Ramp), [(1.0, 0.25), (0.5, 0.125), (0.0, 0.0), (0.5, 0.0)]);
+        assert_eq!(run(TriggerMode::Legato), [(1.0, 0.5), (1.0, 0.75), (1.0, 1.0), (1.0, 1.0)]);
+    }
+    #[test]
+    fn test_vector_osc() {
+        use crate::Proc;
+        use crate::modulate::Modulated;
+        use crate::osc::{VectorOsc, VectorOscSpec};
+        use crate::wave::WaveShape;
+        let mut env = VectorEnv::<3>::default();
+        let spec = spec();
+        let mut osc = VectorOsc::<WaveShape>::default();
+        let mut osc_spec = VectorOscSpec::<WaveShape>::default();
+        *osc_spec.get_corner_mut(0).get_wave_mut() = WaveShape::Saw;
+        *osc_spec.get_corner_mut(1).get_wave_mut() = WaveShape::Pulse;
+        // the env sweeps from the saw corner over to the pulse corner:
+        let mut out = vec![];
+        for _ in 0..3 {
+            let mut child = osc_spec.modulated();
+            child.set_xy(env.gen(&spec));
+            out.push(osc.proc(&child, 0.2));
+        }
+        assert_eq!(out, [0.0, 0.28284271247461895, 0.7500000000000004]);
+    }
+}
@@ -4,8 +4,9 @@
 pub mod svf;
 
 /// Enum representing a filter's type (high-pass, low-pass, etc.).
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Default)]
 pub enum FilterType {
+    #[default]
     Low,
     High,
     Peak,
@@ -14,7 +15,3 @@ pub enum FilterType {
     All,
     MagicPeak,
 }
-
-impl Default for FilterType {
-    fn default() -> Self { Self::Low }
-}
@@ -4,6 +4,10 @@ use crate::{F, Scale, Seconds, Sample, Proc};
 use crate::clock::{Clock, SetClock};
 use crate::util::Time;
 
+/// Envs triggered by an [EnvFollower].
+mod trigger;
+pub use self::trigger::{EnvTrigger, EnvTriggerSpec};
+
 /// Engages an envelope when input crosses an amplitude threshold.
 #[derive(Debug, Default)]
 pub struct EnvFollower {
@@ -0,0 +1,133 @@
+use crate::{Scale, Seconds, Gen, Proc};
+use crate::clock::{Clock, SetClock};
+use crate::env::HoldRelease;
+use crate::util::Time;
+
+use super::{EnvFollower, EnvFollowerSpec};
+
+/// Audio-triggered env: holds the env when the follower's input crosses its threshold,
+/// and releases it once the follower lets go.
+///
+/// After each trigger, further triggers are ignored for the spec's lockout time,
+/// so e.g. a drum hit's ringing tail can't retrigger it.
+#[derive(Debug)]
+pub struct EnvTrigger<E> {
+    follower: EnvFollower,
+    env: E,
+    // follower output last sample, to find edges:
+    gate: bool,
+    // samples left before we can trigger again:
+    lockout: usize,
+}
+
+impl<E: HoldRelease + Default> Default for EnvTrigger<E> {
+    fn default() -> Self {
+        // start released, so we stay silent until the first trigger:
+        let mut env = E::default();
+        env.release();
+        Self { follower: EnvFollower::default(), env, gate: false, lockout: 0 }
+    }
+}
+
+impl<E> EnvTrigger<E> {
+    crate::getters!(env, get_env, get_env_mut, E);
+}
+
+impl<I, E> Proc<I, Scale> for EnvTrigger<E>
+where
+    EnvFollower: Proc<I, bool, Spec = EnvFollowerSpec>,
+    E: HoldRelease + Gen<Scale>,
+{
+    type Spec = EnvTriggerSpec<E::Spec>;
+    fn proc(&mut self, spec: &Self::Spec, input: I) -> Scale {
+        let gate = self.follower.proc(&spec.follower, input);
+        if gate && !self.gate {
+            if self.lockout == 0 {
+                self.env.hold();
+                self.lockout = spec.lockout.get_samples();
+            }
+        } else if !gate && self.gate {
+            self.env.release();
+        }
+        self.gate = gate;
+        self.lockout = self.lockout.saturating_sub(1);
+        self.env.gen(&spec.env)
+    }
+}
+
+/// Spec for [EnvTrigger].
+#[derive(Debug, Default)]
+pub struct EnvTriggerSpec<S> {
+    follower: EnvFollowerSpec,
+    env: S,
+    /// minimum time between triggers
+    lockout: Time,
+}
+
+impl<S: SetClock> SetClock for EnvTriggerSpec<S> {
+    fn set_clock(&mut self, clock: &Clock) {
+        self.follower.set_clock(clock);
+        self.env.set_clock(clock);
+        self.lockout.set_clock(clock);
+    }
+}
+
+impl<S> EnvTriggerSpec<S> {
+    crate::getters!(follower, get_follower, get_follower_mut, EnvFollowerSpec);
+    crate::getters!(env, get_env, get_env_mut, S);
+
+    pub fn get_lockout(&self) -> Seconds {
+        self.lockout.get()
+    }
+
+    /// Set how long to ignore new triggers for after each trigger.
+    pub fn set_lockout(&mut self, lockout: Seconds) {
+        self.lockout.set(lockout);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::env::{Adsr, AdsrSpec, AdsrStage};
+    use crate::Sample;
+    use super::*;
+    fn spec() -> EnvTriggerSpec<AdsrSpec> {
+        let mut spec = EnvTriggerSpec::<AdsrSpec>::default();
+        spec.get_follower_mut().set_threshold(0.5);
+        spec.get_env_mut().set(AdsrStage::A, 0.2);
+        spec.get_env_mut().set(AdsrStage::S, 1.0);
+        spec.get_env_mut().set(AdsrStage::R, 0.2);
+        spec.set_clock(&Clock::new(10.0));
+        spec
+    }
+    fn run(env: &mut EnvTrigger<Adsr>, spec: &EnvTriggerSpec<AdsrSpec>, input: &[Sample]) -> Vec<Scale> {
+        input.iter().map(|&sample| env.proc(spec, sample)).collect()
+    }
+    #[test]
+    fn test_sizes() {
+        use std::mem::size_of;
+        assert_eq!(size_of::<EnvTrigger<Adsr>>(), 64);
+        assert_eq!(size_of::<EnvTriggerSpec<AdsrSpec>>(), 168);
+    }
+    #[test]
+    fn test_env_trigger() {
+        let spec = spec();
+        let mut env = EnvTrigger::<Adsr>::default();
+        // silent until the input crosses the threshold, then held until it drops:
+        let out = run(&mut env, &spec, &[0.0, 0.0, 1.0, 1.0, 1.0, 1.0, 1.0, 0.0, 0.0, 0.0]);
+        assert_eq!(out, [0.0, 0.0, 0.0, 0.5, 1.0, 1.0, 1.0, 1.0, 0.5, 0.0]);
+        // stereo input works too:
+        let out: Vec<_> = [[0.0, 0.6], [0.0, 0.0]].into_iter().map(|input| env.proc(&spec, input)).collect();
+        assert_eq!(out, [0.0, 0.5]);
+    }
+    #[test]
+    fn test_lockout() {
+        let mut spec = spec();
+        spec.set_lockout(0.5);
+        assert_eq!(spec.get_lockout(), 0.5);
+        let mut env = EnvTrigger::<Adsr>::default();
+        // the second hit comes too soon, so the env stays released:
+        let out = run(&mut env, &spec, &[1.0, 0.0, 0.0, 1.0, 1.0, 0.0, 1.0, 1.0]);
+        assert_eq!(out, [0.0, 0.5, 0.0, 0.0, 0.0, 0.0, 0.0, 0.5]);
+    }
+}
@@ -0,0 +1,149 @@
+use crate::{Sample, Phase, Gen, Proc};
+use crate::clock::{Clock, SetClock};
+use crate::phase::{Phasor, PhaseAccumulator};
+
+use super::{BasicLfo, BasicLfoSpec, LfoMode};
+
+/// A bank of `N` [BasicLfo]s, one per voice slot, sharing a single spec.
+///
+/// Produces a value for every voice slot each sample. In [LfoBankMode::Global],
+/// only the first lfo is run, and its value is copied to every slot.
+#[derive(Debug)]
+pub struct LfoBank<O, const N: usize, P = Phasor> {
+    lfos: [BasicLfo<O, P>; N],
+}
+
+impl<O: Default, const N: usize, P: Default> Default for LfoBank<O, N, P> {
+    fn default() -> Self {
+        Self {
+            lfos: core::array::from_fn(|_| BasicLfo::default()),
+        }
+    }
+}
+
+impl<O: Proc<Phase, Sample>, const N: usize, P: PhaseAccumulator> Gen<[Sample; N]> for LfoBank<O, N, P> {
+    type Spec = LfoBankSpec<O::Spec>;
+    fn gen(&mut self, spec: &Self::Spec) -> [Sample; N] {
+        match spec.mode {
+            LfoBankMode::Global => [self.lfos[0].gen(&spec.lfo); N],
+            LfoBankMode::VoiceFree | LfoBankMode::VoiceRetrigger => {
+                core::array::from_fn(|i| self.lfos[i].gen(&spec.lfo))
+            },
+        }
+    }
+}
+
+impl<O, const N: usize, P: PhaseAccumulator> LfoBank<O, N, P> {
+    crate::index_getters!(lfos, get_lfo, get_lfo_mut, BasicLfo<O, P>);
+
+    /// Respond to a note starting in the given voice slot.
+    ///
+    /// A global lfo keeps running; a per-voice free lfo only restarts its delay and fade
+    /// (as in [LfoMode::Free]); a per-voice retriggered lfo restarts as set by the spec's mode,
+    /// treating [LfoMode::Free] as [LfoMode::Retrigger].
+    pub fn trigger_voice<S>(&mut self, spec: &LfoBankSpec<S>, voice: usize, from_silence: bool) {
+        let mode = match spec.mode {
+            LfoBankMode::Global => return,
+            LfoBankMode::VoiceFree => LfoMode::Free,
+            LfoBankMode::VoiceRetrigger => match spec.lfo.get_mode() {
+                LfoMode::Free => LfoMode::Retrigger,
+                mode => mode,
+            },
+        };
+        self.lfos[voice].trigger_as(&spec.lfo, mode, from_silence);
+    }
+}
+
+/// How an [LfoBank] shares its lfos between voices.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LfoBankMode {
+    /// One lfo for every voice, so they all share the same phase.
+    #[default]
+    Global,
+    /// One lfo per voice, running freely through note-ons.
+    VoiceFree,
+    /// One lfo per voice, restarted on each of that voice's note-ons.
+    VoiceRetrigger,
+}
+
+/// Spec for [LfoBank].
+#[derive(Debug, Default)]
+pub struct LfoBankSpec<S> {
+    lfo: BasicLfoSpec<S>,
+    mode: LfoBankMode,
+}
+
+impl<S> SetClock for LfoBankSpec<S> {
+    fn set_clock(&mut self, clock: &Clock) {
+        self.lfo.set_clock(clock);
+    }
+}
+
+impl<S> LfoBankSpec<S> {
+    crate::getters!(lfo, get_lfo, get_lfo_mut, BasicLfoSpec<S>);
+    crate::accessors!(mode, get_mode, set_mode, LfoBankMode);
+}
+
+#[cfg(test)]
+mod test {
+    use crate::osc::{SingleOsc, SingleOscSpec};
+    use crate::lfo::{RandomOsc, RandomOscSpec};
+    use crate::wave::WaveShape;
+    use super::*;
+    fn spec() -> LfoBankSpec<SingleOscSpec> {
+        let mut spec = LfoBankSpec::<SingleOscSpec>::default();
+        *spec.get_lfo_mut().get_osc_mut().get_wave_mut() = WaveShape::Saw;
+        spec.set_clock(&Clock::new(4.0));
+        spec.get_lfo_mut().set_rate(1.0);
+        spec
+    }
+    #[test]
+    fn test_sizes() {
+        use std::mem::size_of;
+        assert_eq!(size_of::<LfoBank<SingleOsc, 4>>(), 128);
+        assert_eq!(size_of::<LfoBankSpec<SingleOscSpec>>(), 136);
+    }
+    #[test]
+    fn test_global() {
+        let mut bank = LfoBank::<SingleOsc, 3>::default();
+        let spec = spec();
+        assert_eq!(bank.gen(&spec), [-1.0; 3]);
+        // note-ons don't touch a global lfo:
+        bank.trigger_voice(&spec, 1, true);
+        assert_eq!(bank.gen(&spec), [-0.5; 3]);
+        // and the other lfos never run:
+        assert_eq!(bank.gen(&spec), [0.0; 3]);
+        assert_eq!(bank.get_lfo_mut(1).gen(spec.get_lfo()), -1.0);
+    }
+    #[test]
+    fn test_voice_free() {
+        let mut bank = LfoBank::<RandomOsc, 2>::default();
+        let mut spec = LfoBankSpec::<RandomOscSpec>::default();
+        spec.set_clock(&Clock::new(4.0));
+        spec.get_lfo_mut().set_rate(1.0);
+        spec.set_mode(LfoBankMode::VoiceFree);
+        // each voice gets its own random sequence:
+        bank.get_lfo_mut(1).get_osc_mut().seed(1);
+        let [a, b] = bank.gen(&spec);
+        assert_ne!(a, b);
+        // and note-ons don't restart the phase:
+        bank.trigger_voice(&spec, 0, false);
+        assert_eq!(bank.gen(&spec), [a, b]);
+    }
+    #[test]
+    fn test_voice_retrigger() {
+        let mut bank = LfoBank::<SingleOsc, 2>::default();
+        let mut spec = spec();
+        spec.set_mode(LfoBankMode::VoiceRetrigger);
+        assert_eq!(bank.gen(&spec), [-1.0, -1.0]);
+        assert_eq!(bank.gen(&spec), [-0.5, -0.5]);
+        // restarting one voice leaves the other alone:
+        bank.trigger_voice(&spec, 1, false);
+        assert_eq!(bank.gen(&spec), [0.0, -1.0]);
+        // one-shots stay one-shots:
+        spec.get_lfo_mut().set_mode(LfoMode::OneShot);
+        bank.trigger_voice(&spec, 0, false);
+        let out: Vec<_> = (0..5).map(|_| bank.gen(&spec)[0]).collect();
+        assert_eq!(out, [-1.0, -0.5, 0.0, 0.5, 0.5]);
+    }
+}
@@ -1,35 +1,127 @@
-use crate::{Hz, Seconds, Sample, Phase, Gen, Proc};
-use crate::clock::{Clock, SetClock};
-use crate::phase::Phasor;
+use crate::{Hz, Seconds, Sample, Scale, Phase, Bpm, Beats, Gen, Proc};
+use crate::clock::{self, Clock, SetClock, Division};
+use crate::phase::{Phasor, PhaseAccumulator};
+use crate::trigger::Trigger;
+use crate::util::Time;
+use crate::osc::{OscCore, OscCoreSpec};
+use crate::wave::WaveCompute;
 
 /// An Lfo.
 ///
 /// Internally it's just an oscillator and a Phasor. The expected usage is the main difference;
 /// where oscs expect to have their phase increment passed in based on a pitch calculation,
 /// Lfos store a rate in the spec and use that to compute the next phase increment.
+/// Swap in a [crate::phase::FixedPhasor] as `P` for drift-free phase.
+///
+/// On [Trigger], restarts according to the spec's [LfoMode],
+/// then waits out the spec's delay and fades in over its fade time.
 // TODO just use PhasorOsc for this w/ a different spec, and impl Gen instead of Proc.
 #[derive(Debug, Default)]
-pub struct BasicLfo<O> {
-    phasor: Phasor,
+pub struct BasicLfo<O, P = Phasor> {
+    phasor: P,
     osc: O,
+    /// samples since the last trigger
+    elapsed: usize,
+    /// how far through its cycle a one-shot is
+    travelled: Phase,
+    /// last output before fading, for one-shots to hold onto
+    last: Sample,
 }
 
-impl<O: Proc<Phase, Sample>> Gen<Sample> for BasicLfo<O> {
+impl<O: Proc<Phase, Sample>, P: PhaseAccumulator> Gen<Sample> for BasicLfo<O, P> {
     type Spec = BasicLfoSpec<O::Spec>;
     fn gen(&mut self, spec: &Self::Spec) -> Sample {
-        if spec.rate == 0.0 {
+        if spec.dphase == 0.0 {
+            return 0.0;
+        }
+
+        let delay = spec.delay.get_samples();
+        if self.elapsed < delay {
+            self.elapsed += 1;
+            // free-running lfos keep their place while they wait:
+            if spec.mode == LfoMode::Free {
+                self.phasor.advance(spec.dphase);
+            }
             return 0.0;
         }
 
-        let phase = self.phasor.advance(spec.dphase);
-        self.osc.proc(&spec.osc, phase)
+        if spec.mode != LfoMode::OneShot || self.travelled < 1.0 {
+            let phase = self.phasor.advance(spec.dphase);
+            self.travelled += spec.dphase;
+            self.last = self.osc.proc(&spec.osc, phase);
+        }
+
+        let fade = spec.fade.get_samples();
+        let faded = self.elapsed - delay;
+        if faded < fade {
+            self.elapsed += 1;
+            self.last * faded as Scale / fade as Scale
+        } else {
+            self.last
+        }
+    }
+}
+
+impl<O: Proc<Phase, Sample>, P: PhaseAccumulator> Trigger for BasicLfo<O, P> {
+    type Spec = BasicLfoSpec<O::Spec>;
+    fn trigger(&mut self, spec: &Self::Spec, from_silence: bool) {
+        self.trigger_as(spec, spec.mode, from_silence);
+    }
+}
+
+impl<O, P: PhaseAccumulator> BasicLfo<O, P> {
+    /// Trigger as if the spec had the given mode.
+    pub(super) fn trigger_as<S>(&mut self, spec: &BasicLfoSpec<S>, mode: LfoMode, from_silence: bool) {
+        match mode {
+            // only start the delay and fade over if the voice was silent:
+            LfoMode::Free => if from_silence {
+                self.elapsed = 0;
+            },
+            LfoMode::Retrigger | LfoMode::OneShot => {
+                self.phasor.set(spec.start);
+                self.elapsed = 0;
+                self.travelled = 0.0;
+            },
+        }
     }
 }
 
-impl<O> BasicLfo<O> {
+/// How a [BasicLfo] responds to triggers.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LfoMode {
+    /// Keep running through triggers; one lfo can be shared by every voice.
+    #[default]
+    Free,
+    /// Restart from the start phase on every trigger.
+    Retrigger,
+    /// Restart on every trigger, run for one cycle, then hold the final value, like an env.
+    OneShot,
+}
+
+impl<O, P: PhaseAccumulator> BasicLfo<O, P> {
+    pub fn get_osc(&self) -> &O {
+        &self.osc
+    }
+
+    pub fn get_osc_mut(&mut self) -> &mut O {
+        &mut self.osc
+    }
+
     pub fn reset(&mut self) {
         self.phasor.set(0.0);
     }
+
+    /// Line the phase up with the host's song position, given in quarter notes,
+    /// e.g. after the host seeks.
+    ///
+    /// Does nothing unless the spec is tempo-synced and set to lock to the song position.
+    pub fn set_position<S>(&mut self, spec: &BasicLfoSpec<S>, position: Beats) {
+        if let (Some(division), true) = (spec.division, spec.lock) {
+            let phase = (position / division.beats()).rem_euclid(1.0);
+            // rem_euclid rounds tiny negative positions up to exactly 1:
+            self.phasor.set(if phase < 1.0 { phase } else { 0.0 });
+        }
+    }
 }
 
 /// Spec for [BasicLfo].
@@ -41,6 +133,20 @@ pub struct BasicLfoSpec<O> {
     tick: Seconds,
     /// user-specified rate in Hz
     rate: Hz,
+    /// host tempo, for synced rates
+    tempo: Bpm,
+    /// if set, overrides rate with a tempo-synced division
+    division: Option<Division>,
+    /// whether to lock the phase to the host's song position
+    lock: bool,
+    /// how to respond to triggers
+    mode: LfoMode,
+    /// phase to restart from on trigger
+    start: Phase,
+    /// time to wait after a trigger before starting
+    delay: Time,
+    /// time to ramp depth up from zero after the delay
+    fade: Time,
     /// phase increment (rate * tick)
     dphase: Phase,
 }
@@ -48,7 +154,10 @@ pub struct BasicLfoSpec<O> {
 impl<O> SetClock for BasicLfoSpec<O> {
     fn set_clock(&mut self, clock: &Clock) {
         self.tick = clock.tick;
-        self.dphase = self.rate * self.tick;
+        self.tempo = clock.tempo;
+        self.update_dphase();
+        self.delay.set_clock(clock);
+        self.fade.set_clock(clock);
     }
 }
 
@@ -68,7 +177,70 @@ impl<O> BasicLfoSpec<O> {
     pub fn set_rate(&mut self, rate: Hz) {
         crate::check_float_nonneg!(rate);
         self.rate = rate;
-        self.dphase = self.tick * self.rate;
+        self.update_dphase();
+    }
+
+    pub fn get_division(&self) -> Option<Division> {
+        self.division
+    }
+
+    /// Sync the rate to the host tempo, or go back to the free rate in Hz with `None`.
+    pub fn set_division(&mut self, division: Option<Division>) {
+        self.division = division;
+        self.update_dphase();
+    }
+
+    crate::accessors!(lock, get_lock, set_lock, bool);
+    crate::accessors!(mode, get_mode, set_mode, LfoMode);
+
+    pub fn get_start(&self) -> Phase {
+        self.start
+    }
+
+    pub fn set_start(&mut self, start: Phase) {
+        crate::check_phase_bounds!(start);
+        self.start = start;
+    }
+
+    pub fn get_delay(&self) -> Seconds {
+        self.delay.get()
+    }
+
+    pub fn set_delay(&mut self, delay: Seconds) {
+        self.delay.set(delay);
+    }
+
+    pub fn get_fade(&self) -> Seconds {
+        self.fade.get()
+    }
+
+    pub fn set_fade(&mut self, fade: Seconds) {
+        self.fade.set(fade);
+    }
+
+    /// Current rate in Hz, whether free or tempo-synced.
+    pub fn effective_rate(&self) -> Hz {
+        match self.division {
+            Some(division) => clock::beats_to_hz(self.tempo, division.beats()),
+            None => self.rate,
+        }
+    }
+
+    fn update_dphase(&mut self) {
+        self.dphase = self.effective_rate() * self.tick;
+    }
+}
+
+impl<W: WaveCompute + Default> BasicLfoSpec<OscCoreSpec<W>> {
+    /// Render one cycle of the lfo's shape into `out`, starting from its start phase,
+    /// using the same oscillator code as [BasicLfo].
+    pub fn preview(&self, out: &mut [Sample]) {
+        let mut osc = OscCore::<W>::default();
+        let dphase = 1.0 / out.len() as Phase;
+        for (i, point) in out.iter_mut().enumerate() {
+            let phase = (self.start + i as Phase * dphase) % 1.0;
+            *point = osc.proc(&self.osc, phase);
+        }
     }
 }
 
@@ -80,8 +252,8 @@ mod test {
     #[test]
     fn test_sizes() {
         use std::mem::size_of;
-        assert_eq!(size_of::<BasicLfo<SingleOsc>>(), 8);
-        assert_eq!(size_of::<BasicLfoSpec<SingleOscSpec>>(), 40);
+        assert_eq!(size_of::<BasicLfo<SingleOsc>>(), 32);
+        assert_eq!(size_of::<BasicLfoSpec<SingleOscSpec>>(), 128);
     }
     #[test]
     fn test_lfo() {
@@ -100,4 +272,127 @@ mod test {
         assert_eq!(lfo.gen(&spec), -1.0);
         assert_eq!(lfo.gen(&spec), 1.0);
     }
+    #[test]
+    fn test_fixed_phasor_lfo() {
+        use crate::phase::FixedPhasor;
+        let mut lfo = BasicLfo::<SingleOsc, FixedPhasor>::default();
+        let mut float_lfo = BasicLfo::<SingleOsc>::default();
+        let mut spec = BasicLfoSpec::<SingleOscSpec>::default();
+        spec.set_clock(&Clock::new(40.0));
+        spec.set_rate(5.0);
+        for _ in 0..8 {
+            assert_eq!(lfo.gen(&spec), float_lfo.gen(&spec));
+        }
+        lfo.reset();
+        assert_eq!(lfo.gen(&spec), -1.0);
+    }
+    #[test]
+    fn test_tempo_sync() {
+        use crate::clock::{NoteValue, NoteModifier};
+        let mut lfo = BasicLfo::<SingleOsc>::default();
+        let mut spec = BasicLfoSpec::<SingleOscSpec>::default();
+        let mut clock = Clock::new(48.0);
+        spec.set_clock(&clock);
+        spec.set_rate(1.0);
+        // an eighth note at 120bpm lasts a quarter of a second:
+        spec.set_division(Some(Division::new(NoteValue::Eighth, NoteModifier::Straight)));
+        assert_eq!(spec.effective_rate(), 4.0);
+        assert_eq!(lfo.gen(&spec), -1.0);
+        assert_eq!(lfo.gen(&spec), -0.6666666666666667);
+        // tempo changes come in through the clock:
+        clock.set_tempo(60.0);
+        spec.set_clock(&clock);
+        assert_eq!(spec.effective_rate(), 2.0);
+        assert_eq!(lfo.gen(&spec), -0.33333333333333337);
+        assert_eq!(lfo.gen(&spec), -0.16666666666666674);
+        // and unsyncing goes back to the free rate:
+        spec.set_division(None);
+        assert_eq!(spec.effective_rate(), 1.0);
+    }
+    #[test]
+    fn test_position_lock() {
+        use crate::clock::{NoteValue, NoteModifier};
+        let mut lfo = BasicLfo::<SingleOsc>::default();
+        let mut spec = BasicLfoSpec::<SingleOscSpec>::default();
+        *spec.osc.get_wave_mut() = WaveShape::Saw;
+        spec.set_clock(&Clock::new(48.0));
+        spec.set_division(Some(Division::new(NoteValue::Half, NoteModifier::Straight)));
+        // not locked yet, so seeking does nothing:
+        lfo.set_position(&spec, 1.0);
+        assert_eq!(lfo.gen(&spec), -1.0);
+        // halfway through a half note:
+        spec.set_lock(true);
+        lfo.set_position(&spec, 9.0);
+        assert_eq!(lfo.gen(&spec), 0.0);
+        // and positions before the start of the song still line up:
+        lfo.set_position(&spec, -0.5);
+        assert_eq!(lfo.gen(&spec), 0.5);
+        // even a hair before, as with some hosts' pre-roll:
+        lfo.set_position(&spec, -1e-17);
+        assert_eq!(lfo.gen(&spec), -1.0);
+    }
+    #[test]
+    fn test_modes() {
+        let mut lfo = BasicLfo::<SingleOsc>::default();
+        let mut spec = BasicLfoSpec::<SingleOscSpec>::default();
+        *spec.osc.get_wave_mut() = WaveShape::Saw;
+        spec.set_clock(&Clock::new(4.0));
+        spec.set_rate(1.0);
+        assert_eq!(lfo.gen(&spec), -1.0);
+        assert_eq!(lfo.gen(&spec), -0.5);
+        // free-running ignores triggers:
+        lfo.trigger(&spec, false);
+        assert_eq!(lfo.gen(&spec), 0.0);
+        // retrigger starts over from the start phase:
+        spec.set_mode(LfoMode::Retrigger);
+        spec.set_start(0.25);
+        lfo.trigger(&spec, false);
+        assert_eq!(lfo.gen(&spec), -0.5);
+        assert_eq!(lfo.gen(&spec), 0.0);
+        // one-shot runs through a single cycle, then holds:
+        spec.set_mode(LfoMode::OneShot);
+        spec.set_start(0.0);
+        lfo.trigger(&spec, false);
+        let out: Vec<_> = (0..6).map(|_| lfo.gen(&spec)).collect();
+        assert_eq!(out, [-1.0, -0.5, 0.0, 0.5, 0.5, 0.5]);
+    }
+    #[test]
+    fn test_delay_fade() {
+        let mut lfo = BasicLfo::<SingleOsc>::default();
+        let mut spec = BasicLfoSpec::<SingleOscSpec>::default();
+        *spec.osc.get_wave_mut() = WaveShape::Pulse;
+        spec.set_clock(&Clock::new(4.0));
+        spec.set_rate(0.5);
+        spec.set_mode(LfoMode::Retrigger);
+        spec.set_delay(0.5);
+        spec.set_fade(1.0);
+        lfo.trigger(&spec, true);
+        let out: Vec<_> = (0..8).map(|_| lfo.gen(&spec)).collect();
+        assert_eq!(out, [0.0, 0.0, 0.0, 0.25, 0.5, 0.75, -1.0, -1.0]);
+        // free-running lfos only fade in again from silence:
+        spec.set_mode(LfoMode::Free);
+        lfo.trigger(&spec, false);
+        assert_eq!(lfo.gen(&spec), -1.0);
+        lfo.trigger(&spec, true);
+        assert_eq!(lfo.gen(&spec), 0.0);
+    }
+    #[test]
+    fn test_preview() {
+        let mut spec = BasicLfoSpec::<SingleOscSpec>::default();
+        *spec.osc.get_wave_mut() = WaveShape::Saw;
+        let mut out = [0.0; 4];
+        spec.preview(&mut out);
+        assert_eq!(out, [-1.0, -0.5, 0.0, 0.5]);
+        // starting from the start phase, as after a retrigger:
+        spec.set_start(0.5);
+        spec.preview(&mut out);
+        assert_eq!(out, [0.0, 0.5, -1.0, -0.5]);
+        // and matching what the lfo itself produces:
+        let mut lfo = BasicLfo::<SingleOsc>::default();
+        spec.set_clock(&Clock::new(4.0));
+        spec.set_rate(1.0);
+        spec.set_mode(LfoMode::Retrigger);
+        lfo.trigger(&spec, true);
+        assert_eq!([(); 4].map(|_| lfo.gen(&spec)), out);
+    }
 }
@@ -0,0 +1,294 @@
+use crate::{F, Hz, Seconds, Sample, Scale, Gen};
+use crate::clock::{Clock, SetClock};
+use crate::phase::Phasor;
+use crate::util::Rng;
+
+// starting point for every system; anywhere off the fixed points will do:
+const START: (F, F, F) = (0.1, 0.0, 0.0);
+// largest integration step per sample, beyond which the systems can blow up:
+const MAX_DT: F = 0.01;
+
+/// Chaotic modulation source: a strange attractor, or the logistic map.
+///
+/// The attractors are integrated every sample, at a speed set by the spec's rate,
+/// which is roughly how many times per second they loop around.
+/// The logistic map takes one step per cycle of the rate, and holds in between.
+/// Output is normalized to -1..1.
+///
+/// Seed each instance differently to send them off on different paths.
+#[derive(Debug, Clone)]
+pub struct ChaosLfo {
+    phasor: Phasor,
+    x: F,
+    y: F,
+    z: F,
+}
+
+impl Default for ChaosLfo {
+    fn default() -> Self {
+        let (x, y, z) = START;
+        Self { phasor: Phasor::default(), x, y, z }
+    }
+}
+
+impl ChaosLfo {
+    /// Go back to the starting point.
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Reset to a starting point nudged by the seed.
+    pub fn seed(&mut self, seed: u64) {
+        self.reset();
+        self.x += Rng::new(seed).next_f() * 0.1;
+    }
+}
+
+impl Gen<Sample> for ChaosLfo {
+    type Spec = ChaosLfoSpec;
+    fn gen(&mut self, spec: &Self::Spec) -> Sample {
+        if spec.rate == 0.0 {
+            return 0.0;
+        }
+        let (x, y, z) = (self.x, self.y, self.z);
+        let (dx, dy, dz) = match spec.system {
+            ChaosSystem::Lorenz => (
+                10.0 * (y - x),
+                x * (28.0 - z) - y,
+                x * y - 8.0 / 3.0 * z,
+            ),
+            ChaosSystem::Rossler => (
+                -y - z,
+                x + 0.2 * y,
+                0.2 + z * (x - 5.7),
+            ),
+            ChaosSystem::DoubleScroll => (
+                15.6 * (y - x - chua_diode(x)),
+                x - y + z,
+                -28.0 * y,
+            ),
+            ChaosSystem::Logistic => {
+                // the map only stays bounded inside 0..1, and 0 and 1 are both fixed points,
+                // so restart if we've been switched here from another system:
+                if !(x > 0.0 && x < 1.0) {
+                    self.x = START.0;
+                }
+                if self.phasor.advance(spec.dphase) < spec.dphase {
+                    self.x = spec.chaos_r * self.x * (1.0 - self.x);
+                }
+                return (self.x * 2.0 - 1.0).clamp(-1.0, 1.0);
+            },
+        };
+        self.x += dx * spec.dt;
+        self.y += dy * spec.dt;
+        self.z += dz * spec.dt;
+        if !self.x.is_finite() {
+            self.reset();
+        }
+        (self.x * spec.system.norm()).clamp(-1.0, 1.0)
+    }
+}
+
+// piecewise-linear nonlinearity of Chua's circuit:
+fn chua_diode(x: F) -> F {
+    const M0: F = -8.0 / 7.0;
+    const M1: F = -5.0 / 7.0;
+    M1 * x + 0.5 * (M0 - M1) * ((x + 1.0).abs() - (x - 1.0).abs())
+}
+
+/// Systems a [ChaosLfo] can run.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ChaosSystem {
+    /// Lorenz attractor: two lobes, flipping between them unpredictably.
+    #[default]
+    Lorenz,
+    /// Rössler attractor: a spiral that occasionally folds over.
+    Rossler,
+    /// Chua's circuit double-scroll attractor: like Lorenz, but more angular.
+    DoubleScroll,
+    /// Logistic map: a stepped sequence, from periodic to chaotic as the chaos param rises.
+    Logistic,
+}
+
+impl ChaosSystem {
+    /// simulated time taken by a typical loop around the attractor
+    fn period(self) -> F {
+        match self {
+            Self::Lorenz       => 0.75,
+            Self::Rossler      => 6.0,
+            Self::DoubleScroll => 2.0,
+            Self::Logistic     => 1.0,
+        }
+    }
+
+    /// scales x to about -1..1
+    fn norm(self) -> Scale {
+        match self {
+            Self::Lorenz       => 1.0 / 20.0,
+            Self::Rossler      => 1.0 / 12.0,
+            Self::DoubleScroll => 1.0 / 2.5,
+            Self::Logistic     => 1.0,
+        }
+    }
+}
+
+/// Spec for [ChaosLfo].
+#[derive(Debug, Clone)]
+pub struct ChaosLfoSpec {
+    system: ChaosSystem,
+    /// tick time, based on sample rate
+    tick: Seconds,
+    /// user-specified rate in Hz
+    rate: Hz,
+    /// user-specified chaos, 0..=1
+    chaos: Scale,
+    /// integration step per sample (rate * tick * period)
+    dt: F,
+    /// phase increment for the logistic map (rate * tick)
+    dphase: F,
+    /// growth param of the logistic map, derived from chaos
+    chaos_r: F,
+}
+
+impl Default for ChaosLfoSpec {
+    fn default() -> Self {
+        let mut spec = Self {
+            system: ChaosSystem::default(),
+            tick: 0.0,
+            rate: 0.0,
+            chaos: 1.0,
+            dt: 0.0,
+            dphase: 0.0,
+            chaos_r: 0.0,
+        };
+        spec.set_chaos(1.0);
+        spec
+    }
+}
+
+impl SetClock for ChaosLfoSpec {
+    fn set_clock(&mut self, clock: &Clock) {
+        self.tick = clock.tick;
+        self.update_dt();
+    }
+}
+
+impl ChaosLfoSpec {
+    pub fn get_system(&self) -> ChaosSystem {
+        self.system
+    }
+
+    pub fn set_system(&mut self, system: ChaosSystem) {
+        self.system = system;
+        self.update_dt();
+    }
+
+    pub fn get_rate(&self) -> Hz {
+        self.rate
+    }
+
+    pub fn set_rate(&mut self, rate: Hz) {
+        crate::check_float_nonneg!(rate);
+        self.rate = rate;
+        self.update_dt();
+    }
+
+    pub fn get_chaos(&self) -> Scale {
+        self.chaos
+    }
+
+    /// Set how chaotic the logistic map is: at 0 it settles into a short repeating pattern,
+    /// and at 1 it never repeats.
+    pub fn set_chaos(&mut self, chaos: Scale) {
+        crate::check_float_01!(chaos);
+        self.chaos = chaos;
+        self.chaos_r = 3.5 + chaos * 0.5;
+    }
+
+    fn update_dt(&mut self) {
+        self.dphase = self.rate * self.tick;
+        self.dt = (self.dphase * self.system.period()).min(MAX_DT);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn test_sizes() {
+        use std::mem::size_of;
+        assert_eq!(size_of::<ChaosLfo>(), 32);
+        assert_eq!(size_of::<ChaosLfoSpec>(), 56);
+    }
+    #[test]
+    fn test_attractors() {
+        for system in [ChaosSystem::Lorenz, ChaosSystem::Rossler, ChaosSystem::DoubleScroll] {
+            let mut lfo = ChaosLfo::default();
+            let mut spec = ChaosLfoSpec::default();
+            spec.set_clock(&Clock::new(1000.0));
+            spec.set_rate(5.0);
+            spec.set_system(system);
+            // should stay bounded, and use a good part of the output range on both sides:
+            let (mut min, mut max) = (0.0, 0.0);
+            for _ in 0..100_000 {
+                let out = lfo.gen(&spec);
+                assert!((-1.0..=1.0).contains(&out));
+                min = out.min(min);
+                max = out.max(max);
+            }
+            assert!(min < -0.5 && max > 0.5, "{:?}: {} to {}", system, min, max);
+        }
+    }
+    #[test]
+    fn test_lorenz() {
+        let mut lfo = ChaosLfo::default();
+        let mut spec = ChaosLfoSpec::default();
+        spec.set_clock(&Clock::new(1000.0));
+        // nothing happens at rate 0:
+        assert_eq!(lfo.gen(&spec), 0.0);
+        spec.set_rate(1.0);
+        assert_eq!(lfo.gen(&spec), 0.004962500000000001);
+        assert_eq!(lfo.gen(&spec), 0.004926068750000001);
+        // seeding sends it off somewhere else:
+        let mut other = ChaosLfo::default();
+        other.seed(1);
+        assert_ne!(other.gen(&spec), lfo.gen(&spec));
+    }
+    #[test]
+    fn test_logistic() {
+        let mut lfo = ChaosLfo::default();
+        let mut spec = ChaosLfoSpec::default();
+        spec.set_clock(&Clock::new(4.0));
+        spec.set_rate(2.0);
+        spec.set_system(ChaosSystem::Logistic);
+        // steps once per cycle:
+        let out: Vec<_> = (0..4).map(|_| lfo.gen(&spec)).collect();
+        assert_eq!(out, [-0.2799999999999999, -0.2799999999999999, 0.8432, 0.8432]);
+        // with no chaos, it settles into a repeating pattern of four:
+        spec.set_chaos(0.0);
+        spec.set_rate(4.0);
+        let out: Vec<_> = (0..1000).map(|_| lfo.gen(&spec)).collect();
+        assert!((out[992] - out[996]).abs() < 1e-9);
+        assert!((out[992] - out[993]).abs() > 0.1);
+        assert!((out[992] - out[994]).abs() > 0.01);
+    }
+    #[test]
+    fn test_switch_to_logistic() {
+        let mut lfo = ChaosLfo::default();
+        let mut spec = ChaosLfoSpec::default();
+        spec.set_clock(&Clock::new(1000.0));
+        spec.set_rate(5.0);
+        // run the Lorenz system well out of the logistic map's range...
+        for _ in 0..1000 {
+            lfo.gen(&spec);
+        }
+        assert!(lfo.x.abs() > 1.0);
+        // ...then switch without a reset; the map restarts rather than blowing up:
+        spec.set_system(ChaosSystem::Logistic);
+        for _ in 0..10_000 {
+            let out = lfo.gen(&spec);
+            assert!((-1.0..=1.0).contains(&out));
+        }
+        assert!(lfo.x > 0.0 && lfo.x < 1.0);
+    }
+}
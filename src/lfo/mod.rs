@@ -1,4 +1,13 @@
 //! Low-frequency oscillator implementations.
 
 mod basic;
-pub use self::basic::{BasicLfo, BasicLfoSpec};
+pub use self::basic::{BasicLfo, BasicLfoSpec, LfoMode};
+
+mod random;
+pub use self::random::{RandomOsc, RandomOscSpec, RandomShape};
+
+mod bank;
+pub use self::bank::{LfoBank, LfoBankSpec, LfoBankMode};
+
+mod chaos;
+pub use self::chaos::{ChaosLfo, ChaosLfoSpec, ChaosSystem};
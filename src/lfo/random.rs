@@ -0,0 +1,186 @@
+use crate::{Phase, Sample, Scale, Proc};
+use crate::util::{Rng, lirp};
+
+const DEFAULT_STEP: Scale = 0.25;
+
+/// Random oscillator for use in a [super::BasicLfo], picking a new random value every cycle.
+///
+/// Takes the phase as input and watches for it to wrap, so resetting the lfo's phase
+/// starts a new cycle (and so a new random value) as usual.
+/// Seed each instance differently to get different sequences.
+#[derive(Debug, Clone)]
+pub struct RandomOsc {
+    rng: Rng,
+    last: Phase,
+    from: Sample,
+    to: Sample,
+}
+
+impl Default for RandomOsc {
+    fn default() -> Self {
+        Self {
+            rng: Rng::default(),
+            // start above any phase, so the first sample always picks a value:
+            last: 1.0,
+            from: 0.0,
+            to: 0.0,
+        }
+    }
+}
+
+impl RandomOsc {
+    pub fn seed(&mut self, seed: u64) {
+        self.rng.seed(seed);
+    }
+
+    /// Pick a new value on the next sample, and forget the walk so far.
+    pub fn reset(&mut self) {
+        self.last = 1.0;
+        self.from = 0.0;
+        self.to = 0.0;
+    }
+}
+
+impl Proc<Phase, Sample> for RandomOsc {
+    type Spec = RandomOscSpec;
+    fn proc(&mut self, spec: &Self::Spec, phase: Phase) -> Sample {
+        if phase < self.last {
+            self.from = self.to;
+            self.to = match spec.shape {
+                RandomShape::Stepped | RandomShape::Smooth => self.rng.bipolar(),
+                RandomShape::Drunk => reflect(self.to + self.rng.bipolar() * spec.step),
+            };
+        }
+        self.last = phase;
+        match spec.shape {
+            RandomShape::Stepped => self.to,
+            RandomShape::Smooth | RandomShape::Drunk => lirp(self.from, self.to, phase),
+        }
+    }
+}
+
+// fold a value that's wandered past -1..=1 back inside:
+fn reflect(val: Sample) -> Sample {
+    if val > 1.0 {
+        2.0 - val
+    } else if val < -1.0 {
+        -2.0 - val
+    } else {
+        val
+    }
+}
+
+/// Shapes of a [RandomOsc].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RandomShape {
+    /// Sample & hold: jump to a new random value every cycle.
+    #[default]
+    Stepped,
+    /// Glide linearly from one random value to the next over each cycle.
+    Smooth,
+    /// Random walk: glide a random step up or down from the last value each cycle.
+    Drunk,
+}
+
+/// Spec for [RandomOsc].
+#[derive(Debug, Clone)]
+pub struct RandomOscSpec {
+    shape: RandomShape,
+    /// largest step a drunk walk can take in one cycle
+    step: Scale,
+}
+
+impl Default for RandomOscSpec {
+    fn default() -> Self {
+        Self {
+            shape: RandomShape::default(),
+            step: DEFAULT_STEP,
+        }
+    }
+}
+
+impl RandomOscSpec {
+    crate::accessors!(shape, get_shape, set_shape, RandomShape);
+
+    pub fn get_step(&self) -> Scale {
+        self.step
+    }
+
+    pub fn set_step(&mut self, step: Scale) {
+        crate::check_float_01!(step);
+        self.step = step;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Gen, clock::{Clock, SetClock}};
+    use crate::lfo::{BasicLfo, BasicLfoSpec};
+    use super::*;
+    #[test]
+    fn test_sizes() {
+        use std::mem::size_of;
+        assert_eq!(size_of::<RandomOsc>(), 32);
+        assert_eq!(size_of::<RandomOscSpec>(), 16);
+    }
+    #[test]
+    fn test_stepped() {
+        let mut osc = RandomOsc::default();
+        let spec = RandomOscSpec::default();
+        let first = osc.proc(&spec, 0.0);
+        assert_eq!(first, 0.5237530525323884);
+        assert_eq!(osc.proc(&spec, 0.5), first);
+        // new cycle, new value:
+        let second = osc.proc(&spec, 0.1);
+        assert_eq!(second, 0.25381628310273485);
+        assert_eq!(osc.proc(&spec, 0.6), second);
+    }
+    #[test]
+    fn test_smooth() {
+        let mut osc = RandomOsc::default();
+        let mut spec = RandomOscSpec::default();
+        spec.set_shape(RandomShape::Smooth);
+        // the first cycle glides up from zero:
+        assert_eq!(osc.proc(&spec, 0.0), 0.0);
+        let first = osc.proc(&spec, 0.5) * 2.0;
+        assert_eq!(first, 0.5237530525323884);
+        // then on from there to the next value:
+        assert_eq!(osc.proc(&spec, 0.0), first);
+        assert_eq!(osc.proc(&spec, 0.5), 0.3887846678175616);
+    }
+    #[test]
+    fn test_drunk() {
+        let mut osc = RandomOsc::default();
+        let mut spec = RandomOscSpec::default();
+        spec.set_shape(RandomShape::Drunk);
+        spec.set_step(0.1);
+        // each cycle ends within a step of the last, and never leaves -1..=1:
+        let mut last = 0.0;
+        for _ in 0..10_000 {
+            osc.proc(&spec, 0.0);
+            let end = osc.proc(&spec, 1.0);
+            assert!((end - last).abs() <= 0.1);
+            assert!((-1.0..=1.0).contains(&end));
+            last = end;
+        }
+    }
+    #[test]
+    fn test_lfo() {
+        let mut lfo = BasicLfo::<RandomOsc>::default();
+        let mut spec = BasicLfoSpec::<RandomOscSpec>::default();
+        spec.set_clock(&Clock::new(4.0));
+        spec.set_rate(2.0);
+        lfo.get_osc_mut().seed(1);
+        let out: Vec<_> = (0..4).map(|_| lfo.gen(&spec)).collect();
+        assert_eq!(out, [-0.4119065562492701, -0.4119065562492701, 0.6865827148111963, 0.6865827148111963]);
+        // same seed, same sequence:
+        let mut other = BasicLfo::<RandomOsc>::default();
+        other.get_osc_mut().seed(1);
+        assert_eq!((0..4).map(|_| other.gen(&spec)).collect::<Vec<_>>(), out);
+        // resetting the lfo's phase starts a new cycle:
+        lfo.gen(&spec);
+        let held = lfo.gen(&spec);
+        lfo.reset();
+        assert_ne!(lfo.gen(&spec), held);
+    }
+}
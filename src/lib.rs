@@ -3,16 +3,16 @@
 //! 
 //! Currently supports the following DSP components:
 //! 
-//! - [Envelope generators](crate::env) (ADSR, Exponential ADSR, DA)
-//! - [Envelope follower](crate::follow::EnvFollower)
+//! - [Envelope generators](crate::env) (ADSR and DAHDSR, linear or exponential; DA; vector; MSEG)
+//! - [Envelope follower](crate::follow::EnvFollower), which can [trigger envelopes](crate::follow::EnvTrigger) from audio
 //! - [Filters](crate::filter) (State-variable)
-//! - [Basic waveforms](crate::wave) (Saw, Sine, Pulse, Triangle)
-//! - [Oscillators](crate::osc) built on basic waveforms
-//! - [Low-frequency oscillators](crate::lfo)
+//! - [Basic waveforms](crate::wave) (Saw, Sine, Pulse, Triangle, Parabolic, Trapezoid, and more)
+//! - [Oscillators](crate::osc) built on basic waveforms, including [phase distortion](crate::osc::PdOsc) and [vector](crate::osc::VectorOsc), with polyblep or [minBLEP](crate::wave::MinBlep) anti-aliasing
+//! - [Low-frequency oscillators](crate::lfo), including [random](crate::lfo::RandomOsc) and [chaotic](crate::lfo::ChaosLfo) ones
 //! - Tools for [parameter modulation](crate::modulate)
 //! - Tools for [pitch manipulation](crate::pitch)
 //! - [Bitmask](crate::util::Bitmask) implementation
-//! - Tools for [sample rate manipulation](crate::clock)
+//! - Tools for [sample rate and tempo](crate::clock)
 //! 
 //! As well as a variety of utilities to support the above.
 //!
@@ -129,6 +129,10 @@ pub type Sample = F;
 pub type Scale = F;
 /// Anything measured in seconds; positive only, probably.
 pub type Seconds = F;
+/// Tempo in quarter notes per minute; positive only.
+pub type Bpm = F;
+/// Song position or duration, in quarter notes.
+pub type Beats = F;
 
 /// A midi note; 0..128.
 pub type Note = u8;
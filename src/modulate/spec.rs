@@ -49,11 +49,11 @@ impl<const M: usize, const N: usize, T: ModAccumulator> ModArraySpec<M, N, T> {
     }
 
     pub fn apply_intensities_add(&self, vals: [Scale; M]) -> [Scale; N] {
-        self.apply_intensities_acc(vals, &LfoAccumulator::default())
+        self.apply_intensities_acc(vals, &LfoAccumulator)
     }
 
     pub fn apply_intensities_mul(&self, vals: [Scale; M]) -> [Scale; N] {
-        self.apply_intensities_acc(vals, &EnvAccumulator::default())
+        self.apply_intensities_acc(vals, &EnvAccumulator)
     }
 
     // TODO add accessors that take m and n args:
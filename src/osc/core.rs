@@ -1,50 +1,110 @@
 use std::marker::PhantomData;
 
 use crate::{Sample, Proc, Phase, Scale};
-use crate::wave::WaveCompute;
+use crate::wave::{WaveCompute, WaveBackend, Polyblep};
 use crate::modulate::Modulated;
+use crate::phase::PhaseAccumulator;
+use crate::util::Rng;
 
-const DEFAULT_PULSEWIDTH: Scale = 0.5;
+/// Neutral tone: every [crate::wave::WaveShape] produces its plain waveform at 0.5.
+const DEFAULT_TONE: Scale = 0.5;
 
 /// Base type of oscillators, generic over [crate::wave::WaveShape] or [crate::wave::WaveSet].
+///
+/// The backend `B` decides how anti-aliased output is produced;
+/// see [crate::wave::Polyblep] and [crate::wave::MinBlep].
 #[derive(Debug, Default)]
-pub struct OscCore<W> {
+pub struct OscCore<W, B = Polyblep> {
+    backend: B,
     _phantom: PhantomData<W>,
 }
 
-impl<W: WaveCompute> Proc<Phase, Sample> for OscCore<W> {
+impl<W, B> OscCore<W, B> {
+    pub fn get_backend(&self) -> &B {
+        &self.backend
+    }
+
+    pub fn get_backend_mut(&mut self) -> &mut B {
+        &mut self.backend
+    }
+}
+
+impl<W, B: WaveBackend<W>> OscCore<W, B> {
+    /// Drop any of the backend's state carried over from earlier output.
+    pub fn clear(&mut self) {
+        self.backend.clear();
+    }
+}
+
+impl<W: WaveCompute, B> Proc<Phase, Sample> for OscCore<W, B> {
     type Spec = OscCoreSpec<W>;
     fn proc(&mut self, spec: &Self::Spec, phase: Phase) -> Sample {
         spec.wave.compute_aliasing(phase, spec.tone)
     }
 }
 
-impl<W: WaveCompute> Proc<(Phase, Phase), Sample> for OscCore<W> {
+impl<W, B: WaveBackend<W>> Proc<(Phase, Phase), Sample> for OscCore<W, B> {
     type Spec = OscCoreSpec<W>;
     fn proc(&mut self, spec: &Self::Spec, (phase, dphase): (Phase, Phase)) -> Sample {
-        spec.wave.compute_polyblep(phase, dphase, spec.tone)
+        self.backend.compute(&spec.wave, phase, dphase, spec.tone)
+    }
+}
+
+/// Where an oscillator's phase should start when its voice is triggered.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum StartPhase {
+    /// Carry on from wherever the phase was.
+    #[default]
+    Free,
+    /// Always restart at the given phase.
+    Reset(Phase),
+    /// Restart at a random phase.
+    Random,
+    /// Restart at the given phase only if the voice was silent, otherwise carry on.
+    FromSilence(Phase),
+}
+
+impl StartPhase {
+    /// Apply this policy to a phasor, returning true if it set the phase.
+    pub fn apply(self, phasor: &mut impl PhaseAccumulator, rng: &mut Rng, from_silence: bool) -> bool {
+        match self {
+            Self::Free => return false,
+            Self::Reset(phase) => phasor.set(phase),
+            Self::Random => phasor.set(rng.next_f()),
+            Self::FromSilence(phase) => if from_silence {
+                phasor.set(phase);
+            } else {
+                return false;
+            },
+        }
+        true
     }
 }
 
 /// Spec for [OscCore].
+///
+/// `tone` is a per-shape timbre control in 0..1; see [crate::wave::WaveShape] for its meaning
+/// on each shape.
 #[derive(Debug, Clone)]
 pub struct OscCoreSpec<W> {
     wave: W,
     tone: Scale,
+    start: StartPhase,
 }
 
 impl<W: Default> Default for OscCoreSpec<W> {
     fn default() -> Self {
         Self {
             wave: W::default(),
-            tone: DEFAULT_PULSEWIDTH,
+            tone: DEFAULT_TONE,
+            start: StartPhase::default(),
         }
     }
 }
 
 impl<W> OscCoreSpec<W> {
     pub const fn new(wave: W, tone: Scale) -> Self {
-        Self { wave, tone }
+        Self { wave, tone, start: StartPhase::Free }
     }
     pub fn get_wave(&self) -> &W {
         &self.wave
@@ -61,6 +121,17 @@ impl<W> OscCoreSpec<W> {
     pub fn set_tone(&mut self, tone: Scale) {
         self.tone = tone;
     }
+
+    pub fn get_start(&self) -> StartPhase {
+        self.start
+    }
+
+    pub fn set_start(&mut self, start: StartPhase) {
+        if let StartPhase::Reset(phase) | StartPhase::FromSilence(phase) = start {
+            crate::check_phase_bounds!(phase);
+        }
+        self.start = start;
+    }
 }
 
 impl<W: Clone> Modulated for OscCoreSpec<W> {
@@ -72,14 +143,15 @@ impl<W: Clone> Modulated for OscCoreSpec<W> {
 
 #[cfg(test)]
 mod test {
-    use crate::wave::{WaveShape, WaveSet};
+    use crate::wave::{WaveShape, WaveSet, MinBlep};
     use super::*;
     #[test]
     fn test_sizes() {
         use std::mem::size_of;
         assert_eq!(size_of::<OscCore<WaveShape>>(), 0);
-        assert_eq!(size_of::<OscCoreSpec<WaveShape>>(), 16);
-        assert_eq!(size_of::<OscCoreSpec<WaveSet>>(), 16);
+        assert_eq!(size_of::<OscCoreSpec<WaveShape>>(), 32);
+        assert_eq!(size_of::<OscCoreSpec<WaveSet>>(), 32);
+        assert_eq!(size_of::<OscCore<WaveShape, MinBlep>>(), 272);
     }
     #[test]
     fn test_osc_wave_shape() {
@@ -89,11 +161,11 @@ mod test {
         // default is tri:
         assert_eq!(osc.proc(&spec, 0.75), 0.0);
         assert_eq!(osc.proc(&spec, 0.8), -0.20000000000000018);
-        // tone should have no effect:
+        // tone skews the tri:
         spec.set_tone(0.1);
-        assert_eq!(osc.proc(&spec, 0.75), 0.0);
-        assert_eq!(osc.proc(&spec, 0.8), -0.20000000000000018);
-        // try a pulse wave (where tone does have an effect):
+        assert_eq!(osc.proc(&spec, 0.75), -0.4444444444444444);
+        assert_eq!(osc.proc(&spec, 0.8), -0.5555555555555556);
+        // try a pulse wave (where tone sets the width):
         *spec.get_wave_mut() = WaveShape::Pulse;
         assert_eq!(osc.proc(&spec, 0.05), 1.0);
         assert_eq!(osc.proc(&spec, 0.15), -1.0);
@@ -126,11 +198,28 @@ mod test {
         // now do both at once:
         spec.get_wave_mut().set_shape(WaveShape::Tri, true);
         assert_eq!(osc.proc(&spec, 0.8), sine_out + tri_out);
-        // add a pulse and compare polyblep output:
+        // add a pulse:
         spec.get_wave_mut().set_shape(WaveShape::Pulse, true);
-        spec.set_tone(0.7);
         // yikes, hope the gain is under control somewhere else...
         assert_eq!(osc.proc(&spec, 0.75), -1.7);
-        assert_eq!(osc.proc(&spec, (0.75, 0.1)), -1.5250000000000004);
+        // tone shapes every wave in the set at once; compare polyblep output:
+        spec.set_tone(0.7);
+        assert_eq!(osc.proc(&spec, 0.75), -0.5333333333333337);
+        assert_eq!(osc.proc(&spec, (0.75, 0.1)), -0.39064124068884887);
+    }
+    #[test]
+    fn test_start_phase() {
+        let mut phasor = crate::phase::Phasor::new(0.3);
+        let mut rng = Rng::new(0);
+        assert!(!StartPhase::Free.apply(&mut phasor, &mut rng, true));
+        assert_eq!(phasor.peek(), 0.3);
+        assert!(StartPhase::Reset(0.25).apply(&mut phasor, &mut rng, false));
+        assert_eq!(phasor.peek(), 0.25);
+        assert!(!StartPhase::FromSilence(0.5).apply(&mut phasor, &mut rng, false));
+        assert_eq!(phasor.peek(), 0.25);
+        assert!(StartPhase::FromSilence(0.5).apply(&mut phasor, &mut rng, true));
+        assert_eq!(phasor.peek(), 0.5);
+        assert!(StartPhase::Random.apply(&mut phasor, &mut rng, false));
+        assert_eq!(phasor.peek(), Rng::new(0).next_f());
     }
 }
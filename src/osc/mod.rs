@@ -1,34 +1,68 @@
 //! Oscillator implementations.
 
 mod core;
-pub use self::core::{OscCore, OscCoreSpec};
+pub use self::core::{OscCore, OscCoreSpec, StartPhase};
+
+mod sub;
+pub use sub::{SubOsc, SubOscSpec, SubOctave, SubShape};
+
+mod vector;
+pub use vector::{VectorOsc, VectorOscSpec, VectorPhasors};
 
 use crate::{Phase, Sample, Proc};
-use crate::phase::Phasor;
-use crate::wave::{WaveShape, WaveSet};
+use crate::phase::{Phasor, PhaseAccumulator};
+use crate::trigger::Trigger;
+use crate::util::Rng;
+use crate::wave::{WaveShape, WaveSet, PdShape, WaveBackend, MinBlep};
 
 /// Oscillator that only ever computes one waveform at a time.
 pub type SingleOsc = OscCore<WaveShape>;
 /// Oscillator that can stack multiple waveforms at once.
 pub type MultiOsc = OscCore<WaveSet>;
 
+/// Phase distortion oscillator, with `tone` as the distortion amount.
+pub type PdOsc = OscCore<PdShape>;
+/// Oscillator that anti-aliases with [MinBlep] rather than polyblep.
+/// Use it with a [PolyblepPhasorOsc] and an [OscCoreSpec] as usual.
+pub type MinBlepOsc<W = WaveShape> = OscCore<W, MinBlep>;
+
 /// Spec for [SingleOsc].
 pub type SingleOscSpec = OscCoreSpec<WaveShape>;
 /// Spec for [MultiOsc].
 pub type MultiOscSpec = OscCoreSpec<WaveSet>;
+/// Spec for [PdOsc].
+pub type PdOscSpec = OscCoreSpec<PdShape>;
 
 // PhasorOscs store a Phasor in their state, and are useful as sound sources.
 // Lfos might want more control over the Phasor
 // (tho honestly I forget why I originally did that - something to do with lag)
 
 /// An oscillator tied to a [Phasor] so it can keep track of phase internally.
+///
+/// Apply the spec's [StartPhase] on note-on via [Trigger].
+/// Swap in a [crate::phase::FixedPhasor] as `P` for drift-free phase.
 #[derive(Debug, Default)]
-pub struct PhasorOsc<O> {
-    phasor: Phasor,
+pub struct PhasorOsc<O, P = Phasor> {
+    phasor: P,
+    rng: Rng,
     osc: O,
 }
 
-impl<O: Proc<Phase, Sample>> Proc<Phase, Sample> for PhasorOsc<O> {
+impl<O, P> PhasorOsc<O, P> {
+    /// Seed the random start phase, e.g. with a voice index.
+    pub fn seed(&mut self, seed: u64) {
+        self.rng.seed(seed);
+    }
+}
+
+impl<W, B, P: PhaseAccumulator> Trigger for PhasorOsc<OscCore<W, B>, P> {
+    type Spec = OscCoreSpec<W>;
+    fn trigger(&mut self, spec: &Self::Spec, from_silence: bool) {
+        spec.get_start().apply(&mut self.phasor, &mut self.rng, from_silence);
+    }
+}
+
+impl<O: Proc<Phase, Sample>, P: PhaseAccumulator> Proc<Phase, Sample> for PhasorOsc<O, P> {
     type Spec = O::Spec;
     fn proc(&mut self, spec: &O::Spec, dphase: Phase) -> Sample {
         let phase = self.phasor.advance(dphase);
@@ -36,20 +70,59 @@ impl<O: Proc<Phase, Sample>> Proc<Phase, Sample> for PhasorOsc<O> {
     }
 }
 
+impl<O: Proc<Phase, Sample>, P: PhaseAccumulator> PhasorOsc<O, P> {
+    /// Like [Proc::proc], but also drive a [SubOsc] from the same phase.
+    /// Returns (main output, sub output).
+    pub fn proc_with_sub(
+        &mut self, spec: &O::Spec, sub: &mut SubOsc, sub_spec: &SubOscSpec, dphase: Phase,
+    ) -> (Sample, Sample) {
+        let phase = self.phasor.advance(dphase);
+        (self.osc.proc(spec, phase), sub.proc(sub_spec, (phase, dphase)))
+    }
+}
+
 /// An oscillator, tied to a [Phasor], that produces anti-aliased output where applicable.
+///
+/// Apply the spec's [StartPhase] on note-on via [Trigger].
+/// Swap in a [crate::phase::FixedPhasor] as `P` for drift-free phase.
 #[derive(Debug, Default)]
-pub struct PolyblepPhasorOsc<O> {
-    phasor: Phasor,
+pub struct PolyblepPhasorOsc<O, P = Phasor> {
+    phasor: P,
+    rng: Rng,
     osc: O,
 }
 
-impl<O> PolyblepPhasorOsc<O> {
+impl<O, P: PhaseAccumulator> PolyblepPhasorOsc<O, P> {
     pub fn reset(&mut self, phase: Phase) {
         self.phasor.set(phase);
     }
 }
 
-impl<O: Proc<(Phase, Phase), Sample>> Proc<Phase, Sample> for PolyblepPhasorOsc<O> {
+impl<W, B: WaveBackend<W>, P> PolyblepPhasorOsc<OscCore<W, B>, P> {
+    /// Drop anything the backend was still playing out, e.g. a [MinBlepOsc]'s residuals
+    /// after a [PolyblepPhasorOsc::reset]. Triggers that reset the phase do this for you.
+    pub fn clear(&mut self) {
+        self.osc.clear();
+    }
+}
+
+impl<O, P> PolyblepPhasorOsc<O, P> {
+    /// Seed the random start phase, e.g. with a voice index.
+    pub fn seed(&mut self, seed: u64) {
+        self.rng.seed(seed);
+    }
+}
+
+impl<W, B: WaveBackend<W>, P: PhaseAccumulator> Trigger for PolyblepPhasorOsc<OscCore<W, B>, P> {
+    type Spec = OscCoreSpec<W>;
+    fn trigger(&mut self, spec: &Self::Spec, from_silence: bool) {
+        if spec.get_start().apply(&mut self.phasor, &mut self.rng, from_silence) {
+            self.osc.clear();
+        }
+    }
+}
+
+impl<O: Proc<(Phase, Phase), Sample>, P: PhaseAccumulator> Proc<Phase, Sample> for PolyblepPhasorOsc<O, P> {
     type Spec = O::Spec;
     fn proc(&mut self, spec: &O::Spec, dphase: Phase) -> Sample {
         let phase = self.phasor.advance(dphase);
@@ -57,13 +130,34 @@ impl<O: Proc<(Phase, Phase), Sample>> Proc<Phase, Sample> for PolyblepPhasorOsc<
     }
 }
 
+impl<O: Proc<(Phase, Phase), Sample>, P: PhaseAccumulator> PolyblepPhasorOsc<O, P> {
+    /// Like [Proc::proc], but also drive a [SubOsc] from the same phase.
+    /// Returns (main output, sub output).
+    pub fn proc_with_sub(
+        &mut self, spec: &O::Spec, sub: &mut SubOsc, sub_spec: &SubOscSpec, dphase: Phase,
+    ) -> (Sample, Sample) {
+        let phase = self.phasor.advance(dphase);
+        (self.osc.proc(spec, (phase, dphase)), sub.proc(sub_spec, (phase, dphase)))
+    }
+}
+
 #[cfg(test)]
 mod test {
     use crate::wave::WaveShape;
     use super::*;
     #[test]
     fn test_size() {
-        assert_eq!(std::mem::size_of::<PhasorOsc<SingleOsc>>(), 8);
+        assert_eq!(std::mem::size_of::<PhasorOsc<SingleOsc>>(), 16);
+        assert_eq!(std::mem::size_of::<PolyblepPhasorOsc<SingleOsc>>(), 16);
+    }
+    #[test]
+    fn test_reset() {
+        // any osc can be reset, not just an OscCore:
+        let mut osc = PolyblepPhasorOsc::<SubOsc>::default();
+        let spec = SubOscSpec::default();
+        osc.reset(0.5);
+        // a quarter of the way through the sub's cycle:
+        assert_eq!(osc.proc(&spec, 0.1), 1.0);
     }
     #[test]
     fn test_osc() {
@@ -86,4 +180,139 @@ mod test {
         assert_eq!(osc.proc(&spec, 0.01), -0.2326240000000228);
         assert_eq!(osc.proc(&spec, 0.01), -1.0);
     }
+    #[test]
+    fn test_trigger() {
+        let mut osc = PhasorOsc::<SingleOsc>::default();
+        let mut spec = SingleOscSpec::default();
+        osc.phasor.set(0.3);
+        // free running by default:
+        osc.trigger(&spec, true);
+        assert_eq!(osc.phasor.peek(), 0.3);
+        spec.set_start(StartPhase::Reset(0.25));
+        osc.trigger(&spec, false);
+        assert_eq!(osc.proc(&spec, 0.1), 0.0);
+        // only from silence:
+        spec.set_start(StartPhase::FromSilence(0.0));
+        osc.trigger(&spec, false);
+        assert_eq!(osc.phasor.peek(), 0.35);
+        osc.trigger(&spec, true);
+        assert_eq!(osc.proc(&spec, 0.1), -1.0);
+        // random phases depend on the seed:
+        let mut osc = PolyblepPhasorOsc::<SingleOsc>::default();
+        let mut other = PolyblepPhasorOsc::<SingleOsc>::default();
+        spec.set_start(StartPhase::Random);
+        osc.seed(1);
+        other.seed(1);
+        osc.trigger(&spec, true);
+        other.trigger(&spec, true);
+        assert_eq!(osc.phasor.peek(), other.phasor.peek());
+        other.seed(2);
+        other.trigger(&spec, true);
+        assert_ne!(osc.phasor.peek(), other.phasor.peek());
+    }
+    #[test]
+    fn test_fixed_phasor_osc() {
+        use crate::phase::FixedPhasor;
+        let mut osc = PhasorOsc::<SingleOsc, FixedPhasor>::default();
+        let mut float_osc = PhasorOsc::<SingleOsc>::default();
+        let spec = SingleOscSpec::default();
+        for _ in 0..4 {
+            assert_eq!(osc.proc(&spec, 0.125), float_osc.proc(&spec, 0.125));
+        }
+        // start phase works the same:
+        let mut spec = spec;
+        spec.set_start(StartPhase::Reset(0.5));
+        osc.trigger(&spec, true);
+        assert_eq!(osc.proc(&spec, 0.25), 1.0);
+        // and running backwards wraps below zero:
+        assert_eq!(osc.proc(&spec, -0.375), 0.0);
+        assert_eq!(osc.proc(&spec, -0.375), 0.5);
+        assert_eq!(osc.proc(&spec, -0.375), -1.0);
+        assert_eq!(osc.proc(&spec, -0.375), 0.5);
+        // polyblep oscs can use it too:
+        let mut osc = PolyblepPhasorOsc::<SingleOsc, FixedPhasor>::default();
+        osc.trigger(&spec, true);
+        assert_eq!(osc.proc(&spec, 0.01), 0.9866666666666667);
+    }
+    #[test]
+    fn test_minblep_osc() {
+        let mut spec = SingleOscSpec::default();
+        *spec.get_wave_mut() = WaveShape::Saw;
+        let mut osc = PolyblepPhasorOsc::<MinBlepOsc>::default();
+        let mut polyblep_osc = PolyblepPhasorOsc::<SingleOsc>::default();
+        osc.reset(0.45);
+        polyblep_osc.reset(0.45);
+        // both match the naive saw until the wrap comes along:
+        for _ in 0..5 {
+            assert_eq!(osc.proc(&spec, 0.1), polyblep_osc.proc(&spec, 0.1));
+        }
+        // polyblep starts correcting one sample before the wrap; minblep leaves that sample alone:
+        assert_eq!(polyblep_osc.proc(&spec, 0.1), 0.6500000000000004);
+        assert_eq!(osc.proc(&spec, 0.1), 0.8999999999999999);
+        // minblep only starts after the wrap, and keeps ringing for a while:
+        assert_eq!(polyblep_osc.proc(&spec, 0.1), -0.6500000000000004);
+        assert_eq!(osc.proc(&spec, 0.1), 1.0999304005362074);
+        assert_eq!(polyblep_osc.proc(&spec, 0.1), -0.7);
+        assert_eq!(osc.proc(&spec, 0.1), 1.2690798749486931);
+        // clearing after a reset drops the ringing, so the new note starts out like a fresh osc:
+        let mut fresh = PolyblepPhasorOsc::<MinBlepOsc>::default();
+        osc.reset(0.45);
+        osc.clear();
+        fresh.reset(0.45);
+        for _ in 0..8 {
+            assert_eq!(osc.proc(&spec, 0.1), fresh.proc(&spec, 0.1));
+        }
+        // as does a trigger that resets the phase:
+        let mut fresh = PolyblepPhasorOsc::<MinBlepOsc>::default();
+        spec.set_start(StartPhase::Reset(0.45));
+        osc.trigger(&spec, false);
+        fresh.trigger(&spec, false);
+        for _ in 0..8 {
+            assert_eq!(osc.proc(&spec, 0.1), fresh.proc(&spec, 0.1));
+        }
+    }
+    #[test]
+    fn test_osc_with_sub() {
+        let mut osc = PolyblepPhasorOsc::<SingleOsc>::default();
+        let mut spec = SingleOscSpec::default();
+        *spec.get_wave_mut() = WaveShape::Pulse;
+        let mut sub = SubOsc::default();
+        let sub_spec = SubOscSpec::default();
+        // sub stays high while the main osc goes through a whole cycle...
+        let out: Vec<_> = (0..8).map(|_| osc.proc_with_sub(&spec, &mut sub, &sub_spec, 0.125)).collect();
+        assert_eq!(out, [
+            (0.0, 0.0), (1.0, 1.0), (1.0, 1.0), (1.0, 1.0),
+            (0.0, 1.0), (-1.0, 1.0), (-1.0, 1.0), (-1.0, 1.0),
+        ]);
+        // ...and flips as the main osc wraps:
+        assert_eq!(osc.proc_with_sub(&spec, &mut sub, &sub_spec, 0.125), (0.0, 0.0));
+        assert_eq!(osc.proc_with_sub(&spec, &mut sub, &sub_spec, 0.125), (1.0, -1.0));
+        // the aliasing osc can drive a sub too:
+        let mut osc = PhasorOsc::<SingleOsc>::default();
+        let mut sub = SubOsc::default();
+        let out: Vec<_> = (0..8).map(|_| osc.proc_with_sub(&spec, &mut sub, &sub_spec, 0.25)).collect();
+        assert_eq!(out, [
+            (1.0, 0.0), (1.0, 1.0), (-1.0, 1.0), (-1.0, 1.0),
+            (1.0, 0.0), (1.0, -1.0), (-1.0, -1.0), (-1.0, -1.0),
+        ]);
+    }
+    #[test]
+    fn test_pd_osc() {
+        let mut osc = PhasorOsc::<PdOsc>::default();
+        let mut spec = PdOscSpec::default();
+        // saw by default, with no distortion at zero tone:
+        spec.set_tone(0.0);
+        assert_eq!(osc.proc(&spec, 0.25), 1.0);
+        assert_eq!(osc.proc(&spec, 0.25), 6.123233995736766e-17);
+        assert_eq!(osc.proc(&spec, 0.25), -1.0);
+        // distortion pulls the bottom of the cosine earlier, so it's on the way back up by 0.75:
+        spec.set_tone(0.5);
+        assert_eq!(osc.proc(&spec, 0.25), 0.49390112631226335);
+        // try a resonant shape with polyblep, just after the window resets:
+        *spec.get_wave_mut() = PdShape::ResoSaw;
+        let mut osc = PolyblepPhasorOsc::<PdOsc>::default();
+        osc.phasor.set(0.99);
+        assert_eq!(osc.proc(&spec, 0.02), 0.11639257972996035);
+        assert_eq!(osc.proc(&spec, 0.02), 0.7271346067339042);
+    }
 }
@@ -0,0 +1,156 @@
+use crate::{Phase, Sample, Proc};
+use crate::wave::{WaveShape, WaveCompute};
+
+/// Sub-oscillator that runs one or two octaves below a master oscillator.
+///
+/// Rather than keeping its own phasor, it takes the master's phase as input
+/// and counts the master's wraps to decide which part of its own, longer cycle it's in,
+/// so it always stays locked to the master.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SubOsc {
+    last: Phase,
+    flip: u8,
+}
+
+impl SubOsc {
+    pub fn reset(&mut self) {
+        self.last = 0.0;
+        self.flip = 0;
+    }
+}
+
+impl Proc<(Phase, Phase), Sample> for SubOsc {
+    type Spec = SubOscSpec;
+    fn proc(&mut self, spec: &Self::Spec, (phase, dphase): (Phase, Phase)) -> Sample {
+        let div = spec.octave.divisor();
+        // reduce every sample, in case the octave changed since the last wrap:
+        self.flip %= div;
+        // a master running backwards (e.g. through-zero FM) wraps the other way,
+        // so step back through the sub's cycle:
+        if phase < self.last && dphase > 0.0 {
+            self.flip = (self.flip + 1) % div;
+        } else if phase > self.last && dphase < 0.0 {
+            self.flip = (self.flip + div - 1) % div;
+        }
+        self.last = phase;
+        let div = div as Phase;
+        let sub_phase = (self.flip as Phase + phase) / div;
+        match spec.shape {
+            SubShape::Square => WaveShape::Pulse.compute_polyblep(sub_phase, dphase.abs() / div, 0.5),
+            SubShape::Sine   => WaveShape::Sine.compute_aliasing(sub_phase, 0.5),
+        }
+    }
+}
+
+/// How far below the master a [SubOsc] runs.
+#[derive(Debug, Clone, Copy, Default)]
+pub enum SubOctave {
+    #[default]
+    Down1,
+    Down2,
+}
+
+impl SubOctave {
+    const fn divisor(self) -> u8 {
+        match self {
+            Self::Down1 => 2,
+            Self::Down2 => 4,
+        }
+    }
+}
+
+/// Waveform of a [SubOsc].
+#[derive(Debug, Clone, Copy, Default)]
+pub enum SubShape {
+    #[default]
+    Square,
+    Sine,
+}
+
+/// Spec for [SubOsc].
+#[derive(Debug, Clone, Default)]
+pub struct SubOscSpec {
+    octave: SubOctave,
+    shape: SubShape,
+}
+
+impl SubOscSpec {
+    pub const fn new(octave: SubOctave, shape: SubShape) -> Self {
+        Self { octave, shape }
+    }
+    crate::accessors!(octave, get_octave, set_octave, SubOctave);
+    crate::accessors!(shape, get_shape, set_shape, SubShape);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn test_sizes() {
+        use std::mem::size_of;
+        assert_eq!(size_of::<SubOsc>(), 16);
+        assert_eq!(size_of::<SubOscSpec>(), 2);
+    }
+    #[test]
+    fn test_sub_square() {
+        let mut sub = SubOsc::default();
+        let spec = SubOscSpec::default();
+        // high for the whole first master cycle (apart from the polyblep around the edges)...
+        assert_eq!(sub.proc(&spec, (0.0, 0.1)), 0.0);
+        assert_eq!(sub.proc(&spec, (0.5, 0.1)), 1.0);
+        assert_eq!(sub.proc(&spec, (0.95, 0.1)), 0.7499999999999982);
+        // ...and low for the second:
+        assert_eq!(sub.proc(&spec, (0.05, 0.1)), -0.7499999999999982);
+        assert_eq!(sub.proc(&spec, (0.5, 0.1)), -1.0);
+        // then back up again:
+        assert_eq!(sub.proc(&spec, (0.2, 0.1)), 1.0);
+    }
+    #[test]
+    fn test_sub_two_octaves() {
+        let mut sub = SubOsc::default();
+        let spec = SubOscSpec::new(SubOctave::Down2, SubShape::Square);
+        let mut out = vec![];
+        for _ in 0..4 {
+            out.push(sub.proc(&spec, (0.2, 0.1)));
+            out.push(sub.proc(&spec, (0.7, 0.1)));
+        }
+        assert_eq!(out, [1.0, 1.0, 1.0, 1.0, -1.0, -1.0, -1.0, -1.0]);
+    }
+    #[test]
+    fn test_octave_change() {
+        let mut sub = SubOsc::default();
+        let mut spec = SubOscSpec::new(SubOctave::Down2, SubShape::Sine);
+        for _ in 0..3 {
+            sub.proc(&spec, (0.2, 0.1));
+            sub.proc(&spec, (0.7, 0.1));
+        }
+        // switching mid-note keeps the sub phase in range:
+        spec.set_octave(SubOctave::Down1);
+        assert_eq!(sub.proc(&spec, (0.7, 0.1)), 0.8090169943749475);
+        assert_eq!(sub.proc(&spec, (0.2, 0.1)), -0.587785252292473);
+    }
+    #[test]
+    fn test_negative_dphase() {
+        let mut sub = SubOsc::default();
+        let mut spec = SubOscSpec::new(SubOctave::Down1, SubShape::Sine);
+        // running backwards, the sub runs backwards through its own cycle too,
+        // stepping back a half at each of the master's backward wraps:
+        let phases = [0.8, 0.4, 0.8, 0.4, 0.8];
+        let out: Vec<_> = phases.into_iter().map(|phase| sub.proc(&spec, (phase, -0.4))).collect();
+        assert_eq!(out, [-0.5877852522924734, -0.9510565162951535, 0.5877852522924732, 0.9510565162951535, -0.5877852522924734]);
+        // square works backwards too, with no negative increments reaching the polyblep:
+        spec.set_shape(SubShape::Square);
+        let out: Vec<_> = phases.into_iter().map(|phase| sub.proc(&spec, (phase, -0.4))).collect();
+        assert_eq!(out, [-0.7499999999999999, -1.0, 0.7500000000000004, 1.0, -0.7499999999999999]);
+    }
+    #[test]
+    fn test_sub_sine() {
+        let mut sub = SubOsc::default();
+        let spec = SubOscSpec::new(SubOctave::Down1, SubShape::Sine);
+        assert_eq!(sub.proc(&spec, (0.5, 0.1)), 1.0);
+        assert_eq!(sub.proc(&spec, (0.25, 0.1)), -0.7071067811865475);
+        // reset goes back to the start of the sub's cycle:
+        sub.reset();
+        assert_eq!(sub.proc(&spec, (0.5, 0.1)), 1.0);
+    }
+}
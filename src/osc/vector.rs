@@ -0,0 +1,231 @@
+use core::f64::consts::FRAC_PI_2;
+
+use crate::{F, Phase, Sample, Scale, Proc};
+use crate::modulate::Modulated;
+use crate::phase::{Phasor, PhaseAccumulator};
+use crate::trigger::Trigger;
+use crate::util::Rng;
+use crate::wave::WaveCompute;
+
+use super::{OscCore, OscCoreSpec};
+
+/// Number of oscillators mixed by a [VectorOsc].
+pub const CORNERS: usize = 4;
+
+/// Vector oscillator: mixes four [OscCore]s sitting at the corners of an X/Y plane,
+/// with equal-power crossfades along each axis.
+///
+/// Corners are ordered bottom-left, bottom-right, top-left, top-right,
+/// i.e. (x, y) = (0, 0), (1, 0), (0, 1), (1, 1).
+/// Drive the position with modulation, or with a [crate::env::VectorEnv].
+#[derive(Debug, Default)]
+pub struct VectorOsc<W, P = Phasor> {
+    phasors: [P; CORNERS],
+    rng: Rng,
+    cores: [OscCore<W>; CORNERS],
+}
+
+impl<W, P> VectorOsc<W, P> {
+    /// Seed the random start phases, e.g. with a voice index.
+    pub fn seed(&mut self, seed: u64) {
+        self.rng.seed(seed);
+    }
+}
+
+impl<W: WaveCompute, P: PhaseAccumulator> Proc<Phase, Sample> for VectorOsc<W, P> {
+    type Spec = VectorOscSpec<W>;
+    fn proc(&mut self, spec: &Self::Spec, dphase: Phase) -> Sample {
+        let mut output = 0.0;
+        match spec.phasors {
+            VectorPhasors::Shared => {
+                let phase = self.phasors[0].advance(dphase);
+                for i in 0..CORNERS {
+                    if spec.gains[i] != 0.0 {
+                        output += spec.gains[i] * self.cores[i].proc(&spec.corners[i], (phase, dphase));
+                    }
+                }
+            }
+            VectorPhasors::Independent => {
+                for i in 0..CORNERS {
+                    // keep every phasor running, even when its corner is silent:
+                    let dphase = dphase * spec.ratios[i];
+                    let phase = self.phasors[i].advance(dphase);
+                    if spec.gains[i] != 0.0 {
+                        output += spec.gains[i] * self.cores[i].proc(&spec.corners[i], (phase, dphase));
+                    }
+                }
+            }
+        }
+        output
+    }
+}
+
+impl<W, P: PhaseAccumulator> Trigger for VectorOsc<W, P> {
+    type Spec = VectorOscSpec<W>;
+    fn trigger(&mut self, spec: &Self::Spec, from_silence: bool) {
+        // a shared phasor follows the first corner's start phase:
+        let count = match spec.phasors {
+            VectorPhasors::Shared => 1,
+            VectorPhasors::Independent => CORNERS,
+        };
+        for i in 0..count {
+            spec.corners[i].get_start().apply(&mut self.phasors[i], &mut self.rng, from_silence);
+        }
+    }
+}
+
+/// Whether a [VectorOsc]'s corners share a phasor or each keep their own.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum VectorPhasors {
+    /// All corners read the same phase, so they always line up.
+    #[default]
+    Shared,
+    /// Each corner keeps its own phase, and can run at its own pitch ratio.
+    Independent,
+}
+
+/// Spec for [VectorOsc].
+#[derive(Debug, Clone)]
+pub struct VectorOscSpec<W> {
+    corners: [OscCoreSpec<W>; CORNERS],
+    /// pitch ratio of each corner, only used with independent phasors
+    ratios: [F; CORNERS],
+    phasors: VectorPhasors,
+    x: Scale,
+    y: Scale,
+    /// cached equal-power gain of each corner
+    gains: [Scale; CORNERS],
+}
+
+impl<W: Default> Default for VectorOscSpec<W> {
+    fn default() -> Self {
+        let mut spec = Self {
+            corners: Default::default(),
+            ratios: [1.0; CORNERS],
+            phasors: VectorPhasors::default(),
+            x: 0.0,
+            y: 0.0,
+            gains: [0.0; CORNERS],
+        };
+        spec.update_gains();
+        spec
+    }
+}
+
+impl<W> VectorOscSpec<W> {
+    crate::index_getters!(corners, get_corner, get_corner_mut, OscCoreSpec<W>);
+    crate::accessors!(phasors, get_phasors, set_phasors, VectorPhasors);
+
+    pub fn get_ratio(&self, corner: usize) -> F {
+        self.ratios[corner]
+    }
+
+    pub fn set_ratio(&mut self, corner: usize, ratio: F) {
+        crate::check_float_pos!(ratio);
+        self.ratios[corner] = ratio;
+    }
+
+    pub fn get_xy(&self) -> (Scale, Scale) {
+        (self.x, self.y)
+    }
+
+    /// Set the position in the plane, with each axis in 0..=1.
+    pub fn set_xy(&mut self, (x, y): (Scale, Scale)) {
+        crate::check_float_01!(x);
+        crate::check_float_01!(y);
+        self.x = x;
+        self.y = y;
+        self.update_gains();
+    }
+
+    pub fn get_gain(&self, corner: usize) -> Scale {
+        self.gains[corner]
+    }
+
+    fn update_gains(&mut self) {
+        let (x0, x1) = equal_power(self.x);
+        let (y0, y1) = equal_power(self.y);
+        self.gains = [x0 * y0, x1 * y0, x0 * y1, x1 * y1];
+    }
+}
+
+impl<W: Clone> Modulated for VectorOscSpec<W> {
+    type Child = Self;
+    fn modulated(&self) -> Self::Child {
+        self.clone()
+    }
+}
+
+// gains of the near and far ends of an axis:
+fn equal_power(pos: Scale) -> (Scale, Scale) {
+    let angle = pos * FRAC_PI_2;
+    // clamp away the tiny leftovers at the ends, so fully-faded corners can be skipped:
+    let gain = |g: Scale| if g < 1e-12 { 0.0 } else { g };
+    (gain(angle.cos()), gain(angle.sin()))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::wave::WaveShape;
+    use crate::osc::StartPhase;
+    use super::*;
+    fn spec() -> VectorOscSpec<WaveShape> {
+        let mut spec = VectorOscSpec::<WaveShape>::default();
+        *spec.get_corner_mut(0).get_wave_mut() = WaveShape::Saw;
+        *spec.get_corner_mut(1).get_wave_mut() = WaveShape::Sine;
+        *spec.get_corner_mut(2).get_wave_mut() = WaveShape::Pulse;
+        *spec.get_corner_mut(3).get_wave_mut() = WaveShape::Tri;
+        spec
+    }
+    #[test]
+    fn test_sizes() {
+        use std::mem::size_of;
+        assert_eq!(size_of::<VectorOsc<WaveShape>>(), 40);
+        assert_eq!(size_of::<VectorOscSpec<WaveShape>>(), 216);
+    }
+    #[test]
+    fn test_gains() {
+        let mut spec = spec();
+        // sitting on a corner, only that corner sounds:
+        assert_eq!([0, 1, 2, 3].map(|i| spec.get_gain(i)), [1.0, 0.0, 0.0, 0.0]);
+        spec.set_xy((1.0, 1.0));
+        assert_eq!([0, 1, 2, 3].map(|i| spec.get_gain(i)), [0.0, 0.0, 0.0, 1.0]);
+        // and the total power stays the same in between:
+        spec.set_xy((0.3, 0.8));
+        let power: Scale = [0, 1, 2, 3].map(|i| spec.get_gain(i).powi(2)).iter().sum();
+        assert!((power - 1.0).abs() < 1e-12);
+        spec.set_xy((0.5, 0.5));
+        assert_eq!(spec.get_gain(0), 0.5000000000000001);
+    }
+    #[test]
+    fn test_vector_osc() {
+        let mut osc = VectorOsc::<WaveShape>::default();
+        let mut spec = spec();
+        // just the saw at the origin:
+        assert_eq!(osc.proc(&spec, 0.1), 0.0);
+        assert_eq!(osc.proc(&spec, 0.1), -0.8);
+        // halfway between saw and sine:
+        spec.set_xy((0.5, 0.0));
+        assert_eq!(osc.proc(&spec, 0.1), 0.24823444325202876);
+        // modulated copies can move around without touching the original:
+        let mut child = spec.modulated();
+        child.set_xy((1.0, 1.0));
+        assert_eq!(osc.proc(&child, 0.1), 0.20000000000000018);
+        assert_eq!(spec.get_xy(), (0.5, 0.0));
+    }
+    #[test]
+    fn test_independent_phasors() {
+        let mut osc = VectorOsc::<WaveShape>::default();
+        let mut spec = spec();
+        spec.set_phasors(VectorPhasors::Independent);
+        spec.set_ratio(1, 2.0);
+        spec.set_xy((1.0, 0.0));
+        // the sine corner runs at twice the pitch:
+        assert_eq!(osc.proc(&spec, 0.125), 0.0);
+        assert_eq!(osc.proc(&spec, 0.125), 1.0);
+        // and each corner can start from its own phase:
+        spec.get_corner_mut(1).set_start(StartPhase::Reset(0.5));
+        osc.trigger(&spec, true);
+        assert_eq!(osc.proc(&spec, 0.125), 1.2246467991473532e-16);
+    }
+}
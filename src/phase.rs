@@ -1,7 +1,19 @@
-//! Phasor implementation.
+//! Phasor implementations.
 
 use crate::Phase;
 
+// one whole cycle, in fixed point:
+const FIXED_ONE: Phase = 4_294_967_296.0;
+
+/// Trait for anything that can keep track of an oscillator's phase.
+pub trait PhaseAccumulator {
+    fn reset(&mut self);
+    fn set(&mut self, phase: Phase);
+    fn peek(&self) -> Phase;
+    /// Return the current phase, then move it forward by `dphase`.
+    fn advance(&mut self, dphase: Phase) -> Phase;
+}
+
 /// Keeps track of current phase, for e.g. an oscillator's waveform.
 #[derive(Debug, Clone, Copy, Default)]
 pub struct Phasor {
@@ -34,6 +46,82 @@ impl Phasor {
     }
 }
 
+impl PhaseAccumulator for Phasor {
+    fn reset(&mut self) {
+        Phasor::reset(self)
+    }
+    fn set(&mut self, phase: Phase) {
+        Phasor::set(self, phase)
+    }
+    fn peek(&self) -> Phase {
+        Phasor::peek(self)
+    }
+    fn advance(&mut self, dphase: Phase) -> Phase {
+        Phasor::advance(self, dphase)
+    }
+}
+
+/// Fixed-point alternative to [Phasor], storing phase as a fraction of 2^32.
+///
+/// Wraps exactly, so it never builds up rounding error,
+/// and two of them advanced by the same `dphase` stay in lockstep forever.
+/// Negative `dphase` runs it backwards.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FixedPhasor {
+    current: u32,
+}
+
+impl FixedPhasor {
+    pub fn reset(&mut self) {
+        self.current = 0;
+    }
+
+    pub fn set(&mut self, phase: Phase) {
+        crate::check_phase_bounds!(phase);
+        self.current = to_fixed(phase);
+    }
+
+    pub fn peek(&self) -> Phase {
+        self.current as Phase / FIXED_ONE
+    }
+
+    pub fn advance(&mut self, dphase: Phase) -> Phase {
+        let current = self.peek();
+        self.current = self.current.wrapping_add(to_fixed(dphase));
+        current
+    }
+}
+
+impl From<Phase> for FixedPhasor {
+    fn from(phase: Phase) -> Self {
+        let mut phasor = Self::default();
+        phasor.set(phase);
+        phasor
+    }
+}
+
+impl PhaseAccumulator for FixedPhasor {
+    fn reset(&mut self) {
+        FixedPhasor::reset(self)
+    }
+    fn set(&mut self, phase: Phase) {
+        FixedPhasor::set(self, phase)
+    }
+    fn peek(&self) -> Phase {
+        FixedPhasor::peek(self)
+    }
+    fn advance(&mut self, dphase: Phase) -> Phase {
+        FixedPhasor::advance(self, dphase)
+    }
+}
+
+// any phase, converted to fixed point and wrapped into 0..1.
+// going through i64 means negative phases wrap exactly, and 1.0 wraps to 0:
+fn to_fixed(phase: Phase) -> u32 {
+    crate::check_float_finite!(phase);
+    (phase * FIXED_ONE).round() as i64 as u32
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -85,4 +173,48 @@ mod test {
         let mut phasor = Phasor::default();
         phasor.set(-1.0);
     }
+    #[test]
+    fn test_fixed_size() {
+        assert_eq!(std::mem::size_of::<FixedPhasor>(), 4);
+    }
+    #[test]
+    fn test_fixed_set_peek() {
+        let mut phasor = FixedPhasor::from(0.25);
+        assert_eq!(phasor.peek(), 0.25);
+        phasor.set(0.5);
+        assert_eq!(phasor.peek(), 0.5);
+        phasor.reset();
+        assert_eq!(phasor.peek(), 0.0);
+    }
+    #[test]
+    fn test_fixed_advance() {
+        let mut phasor = FixedPhasor::default();
+        assert_eq!(phasor.advance(0.25), 0.0);
+        assert_eq!(phasor.advance(0.5), 0.25);
+        assert_eq!(phasor.advance(0.5), 0.75);
+        // exact wrap:
+        assert_eq!(phasor.peek(), 0.25);
+        phasor.advance(1.0);
+        assert_eq!(phasor.peek(), 0.25);
+        // negative increments go backwards, wrapping below zero:
+        phasor.advance(-0.5);
+        assert_eq!(phasor.peek(), 0.75);
+    }
+    #[test]
+    fn test_no_drift() {
+        // float phasors build up error over a long note; fixed ones come back exactly:
+        let dphase = 440.0 / 44_100.0;
+        let mut phasor = FixedPhasor::from(0.1);
+        let mut reverse = FixedPhasor::from(0.1);
+        for _ in 0..44_100 * 60 {
+            phasor.advance(dphase);
+            reverse.advance(-dphase);
+        }
+        for _ in 0..44_100 * 60 {
+            phasor.advance(-dphase);
+            reverse.advance(dphase);
+        }
+        assert_eq!(phasor.peek(), FixedPhasor::from(0.1).peek());
+        assert_eq!(reverse.peek(), phasor.peek());
+    }
 }
@@ -0,0 +1,203 @@
+use crate::{F, Hz, Phase, Seconds, Proc};
+use crate::clock::{Clock, SetClock};
+use crate::phase::Phasor;
+use crate::util::Rng;
+
+const CENTS_TO_SEMIS: F = 0.01;
+const DEFAULT_RATE: Hz = 0.5;
+
+/// Analog-style pitch drift for a single voice: a slow random walk,
+/// plus a little jitter that changes once per oscillator cycle.
+///
+/// Takes the voice's phase increment (to keep track of its cycles)
+/// and returns a pitch offset in semitones, suitable for [super::apply_offset]
+/// or adding into one of the offsets passed to [super::PitchCache].
+/// When both depth and jitter are zero, returns exactly 0.0 so the cache stays warm.
+///
+/// Seed each voice differently so they drift apart.
+#[derive(Debug, Default, Clone)]
+pub struct Drift {
+    rng: Rng,
+    phasor: Phasor,
+    walk: F,
+    jitter: F,
+}
+
+impl Drift {
+    pub fn seed(&mut self, seed: u64) {
+        self.rng.seed(seed);
+    }
+
+    pub fn reset(&mut self) {
+        self.phasor.reset();
+        self.walk = 0.0;
+        self.jitter = 0.0;
+    }
+}
+
+impl Proc<Phase, F> for Drift {
+    type Spec = DriftSpec;
+    fn proc(&mut self, spec: &Self::Spec, dphase: Phase) -> F {
+        if spec.depth == 0.0 && spec.jitter == 0.0 {
+            return 0.0;
+        }
+        // random walk that keeps getting pulled back towards zero:
+        self.walk += spec.walk_noise * self.rng.bipolar() - spec.walk_coef * self.walk;
+        // the osc has just started a new cycle:
+        if self.phasor.advance(dphase) < dphase {
+            self.jitter = self.rng.bipolar();
+        }
+        (self.walk * spec.depth + self.jitter * spec.jitter) * CENTS_TO_SEMIS
+    }
+}
+
+/// Spec for [Drift].
+#[derive(Debug, Clone)]
+pub struct DriftSpec {
+    /// typical size of the random walk, in cents
+    depth: F,
+    /// largest per-cycle jitter, in cents
+    jitter: F,
+    /// roughly how often the random walk changes direction, in Hz
+    rate: Hz,
+    tick: Seconds,
+    /// how hard the walk is pulled back to zero each sample
+    walk_coef: F,
+    /// size of each step of the walk, scaled so it settles at a std deviation of about 1
+    walk_noise: F,
+}
+
+impl Default for DriftSpec {
+    fn default() -> Self {
+        Self {
+            depth: 0.0,
+            jitter: 0.0,
+            rate: DEFAULT_RATE,
+            tick: 0.0,
+            walk_coef: 0.0,
+            walk_noise: 0.0,
+        }
+    }
+}
+
+impl SetClock for DriftSpec {
+    fn set_clock(&mut self, clock: &Clock) {
+        self.tick = clock.tick;
+        self.update_walk();
+    }
+}
+
+impl DriftSpec {
+    pub fn get_depth(&self) -> F {
+        self.depth
+    }
+
+    pub fn set_depth(&mut self, cents: F) {
+        crate::check_float_nonneg!(cents);
+        self.depth = cents;
+    }
+
+    pub fn get_jitter(&self) -> F {
+        self.jitter
+    }
+
+    pub fn set_jitter(&mut self, cents: F) {
+        crate::check_float_nonneg!(cents);
+        self.jitter = cents;
+    }
+
+    pub fn get_rate(&self) -> Hz {
+        self.rate
+    }
+
+    pub fn set_rate(&mut self, rate: Hz) {
+        crate::check_float_nonneg!(rate);
+        self.rate = rate;
+        self.update_walk();
+    }
+
+    fn update_walk(&mut self) {
+        self.walk_coef = (self.rate * self.tick).min(1.0);
+        // uniform noise has a variance of 1/3:
+        self.walk_noise = (6.0 * self.walk_coef).sqrt();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::pitch::PitchCache;
+    #[test]
+    fn test_sizes() {
+        use std::mem::size_of;
+        assert_eq!(size_of::<Drift>(), 32);
+        assert_eq!(size_of::<DriftSpec>(), 48);
+    }
+    #[test]
+    fn test_drift_off() {
+        let mut drift = Drift::default();
+        let mut spec = DriftSpec::default();
+        spec.set_clock(&Clock::new(44_100.0));
+        let mut cache = PitchCache::default();
+        let dphase = cache.process(0.01, 0.0, 0.0, 0.0, 0.0);
+        for _ in 0..1000 {
+            let offset = drift.proc(&spec, 0.01);
+            assert_eq!(offset, 0.0);
+            assert_eq!(cache.process(0.01, 0.0, offset, 0.0, 0.0), dphase);
+        }
+    }
+    #[test]
+    fn test_drift() {
+        let mut drift = Drift::default();
+        let mut spec = DriftSpec::default();
+        spec.set_clock(&Clock::new(44_100.0));
+        spec.set_depth(10.0);
+        assert_eq!(drift.proc(&spec, 0.01), 0.00043198423695494655);
+        assert_eq!(drift.proc(&spec, 0.01), -0.00019079523825957088);
+        // the walk should wander around, staying within a few multiples of depth:
+        let mut sum_sq = 0.0;
+        for _ in 0..441_000 {
+            let semis = drift.proc(&spec, 0.01);
+            assert!(semis.abs() < 0.5);
+            sum_sq += semis * semis;
+        }
+        let std_dev = (sum_sq / 441_000.0).sqrt();
+        assert!(std_dev > 0.03 && std_dev < 0.2);
+    }
+    #[test]
+    fn test_jitter() {
+        let mut drift = Drift::default();
+        let mut spec = DriftSpec::default();
+        spec.set_clock(&Clock::new(44_100.0));
+        spec.set_jitter(5.0);
+        spec.set_rate(0.0);
+        // jitter holds for a whole cycle, then changes:
+        let first = drift.proc(&spec, 0.25);
+        assert_eq!(first, 0.012690814155136744);
+        assert_eq!(drift.proc(&spec, 0.25), first);
+        assert_eq!(drift.proc(&spec, 0.25), first);
+        assert_eq!(drift.proc(&spec, 0.25), first);
+        let second = drift.proc(&spec, 0.25);
+        assert_ne!(second, first);
+        assert!(second.abs() <= 0.05);
+    }
+    #[test]
+    fn test_seed() {
+        let mut spec = DriftSpec::default();
+        spec.set_clock(&Clock::new(44_100.0));
+        spec.set_depth(10.0);
+        spec.set_jitter(1.0);
+        let mut a = Drift::default();
+        let mut b = Drift::default();
+        a.seed(1);
+        b.seed(1);
+        for _ in 0..100 {
+            assert_eq!(a.proc(&spec, 0.1), b.proc(&spec, 0.1));
+        }
+        b.seed(2);
+        assert_ne!(a.proc(&spec, 0.1), b.proc(&spec, 0.1));
+        // and the offset should work with apply_offset as usual:
+        let semis = a.proc(&spec, 0.1);
+        assert_eq!(crate::pitch::apply_offset(0.1, semis), 0.0999228037305224);
+    }
+}
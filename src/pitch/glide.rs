@@ -2,19 +2,14 @@ use crate::{Seconds, Gen, F, Phase};
 use crate::clock::{Clock, SetClock};
 
 /// Current state of a pitch glide.
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub enum GlideState {
+    #[default]
     Init,
     Steady(Phase),
     Glide(Glide),
 }
 
-impl Default for GlideState {
-    fn default() -> Self {
-        Self::Init
-    }
-}
-
 impl Gen<Phase> for GlideState {
     type Spec = GlideSpec;
     fn gen(&mut self, spec: &Self::Spec) -> Phase {
@@ -13,6 +13,9 @@ pub use self::octave::{OctaveSpec, Octave};
 mod glide;
 pub use self::glide::{GlideState, Glide, GlideSpec};
 
+mod drift;
+pub use self::drift::{Drift, DriftSpec};
+
 use crate::F;
 
 /// Apply an offset in semitones to a base pitch (or phase increment).
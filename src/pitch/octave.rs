@@ -4,17 +4,12 @@ use crate::{Note, F};
 const MIDI_NOTE_CEILING: u8 = 128;
 
 /// Represents an octave offset.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum Octave {
+    #[default]
     Base, Up(u8), Down(u8),
 }
 
-impl Default for Octave {
-    fn default() -> Self {
-        Self::Base
-    }
-}
-
 impl From<i8> for Octave {
     fn from(val: i8) -> Self {
         match val {
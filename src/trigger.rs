@@ -1,4 +1,4 @@
-//! Midi note trigger implementation.
+//! Midi note trigger implementation, and a trait for components that respond to triggers.
 
 use crate::{Note, Vel};
 
@@ -8,3 +8,11 @@ pub struct NoteOn {
     pub note: Note,
     pub vel: Vel,
 }
+
+/// Trait for components that need to do something when a voice is triggered, e.g. on note-on.
+///
+/// `from_silence` should be true if the voice wasn't already sounding.
+pub trait Trigger {
+    type Spec;
+    fn trigger(&mut self, spec: &Self::Spec, from_silence: bool);
+}
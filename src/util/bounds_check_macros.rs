@@ -8,7 +8,7 @@
 macro_rules! check_phase_bounds {
     ($phase:expr) => {
         debug_assert!(
-            $phase.is_finite() && $phase >= 0.0 && $phase < 1.0,
+            $phase.is_finite() && (0.0..1.0).contains(&$phase),
             "Phase value {} is not in valid range 0..1",
             $phase,
         )
@@ -20,13 +20,25 @@ macro_rules! check_phase_bounds {
 macro_rules! check_float_01 {
     ($val:expr) => {
         debug_assert!(
-            $val.is_finite() && $val >= 0.0 && $val <= 1.0,
+            $val.is_finite() && (0.0..=1.0).contains(&$val),
             "Float value {} is not in valid range 0..=1",
             $val,
         )
     }
 }
 
+/// Check that float values are in range `-1..=1`.
+#[macro_export]
+macro_rules! check_float_bipolar {
+    ($val:expr) => {
+        debug_assert!(
+            $val.is_finite() && (-1.0..=1.0).contains(&$val),
+            "Float value {} is not in valid range -1..=1",
+            $val,
+        )
+    }
+}
+
 /// Check that float values representing Hz are positive and non-zero.
 #[macro_export]
 macro_rules! check_hz_bounds {
@@ -14,6 +14,9 @@ pub use self::midi_note_pitches::MIDI_NOTE_PITCHES;
 mod time;
 pub use self::time::Time;
 
+mod random;
+pub use self::random::Rng;
+
 mod accessor_macros;
 mod bounds_check_macros;
 
@@ -0,0 +1,98 @@
+use crate::F;
+
+// arbitrary, but nonzero, so default Rngs still produce something:
+const DEFAULT_SEED: u64 = 0x2545_f491_4f6c_dd1d;
+
+/// Small, fast, seedable pseudo-random number generator (xorshift64*).
+///
+/// Not remotely suitable for anything but audio,
+/// but cheap enough to keep one per voice and call every sample.
+#[derive(Debug, Clone, Copy)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Default for Rng {
+    fn default() -> Self {
+        Self::new(DEFAULT_SEED)
+    }
+}
+
+impl Rng {
+    /// Create an Rng from any seed, including zero.
+    pub fn new(seed: u64) -> Self {
+        let mut rng = Self { state: 0 };
+        rng.seed(seed);
+        rng
+    }
+
+    /// Restart the sequence from a new seed.
+    pub fn seed(&mut self, seed: u64) {
+        // run the seed through splitmix64, so similar seeds (e.g. voice indexes)
+        // give unrelated sequences, and a zero seed doesn't give a zero state:
+        let mut z = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^= z >> 31;
+        self.state = if z == 0 { DEFAULT_SEED } else { z };
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    /// Random float in 0..1.
+    pub fn next_f(&mut self) -> F {
+        // top 53 bits fill the mantissa exactly:
+        (self.next_u64() >> 11) as F * (1.0 / (1u64 << 53) as F)
+    }
+
+    /// Random float in -1..1.
+    pub fn bipolar(&mut self) -> F {
+        self.next_f() * 2.0 - 1.0
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn test_size() {
+        assert_eq!(std::mem::size_of::<Rng>(), 8);
+    }
+    #[test]
+    fn test_rng() {
+        let mut rng = Rng::new(0);
+        assert_eq!(rng.next_u64(), 8916199331640804048);
+        assert_eq!(rng.next_f(), 0.8691389606829488);
+        assert_eq!(rng.bipolar(), 0.40448668097888096);
+        // same seed, same sequence:
+        let mut a = Rng::new(1);
+        let mut b = Rng::new(1);
+        for _ in 0..10 {
+            assert_eq!(a.next_u64(), b.next_u64());
+        }
+        // reseeding starts over:
+        a.seed(1);
+        assert_eq!(a.next_u64(), Rng::new(1).next_u64());
+        // different seeds, different sequences:
+        assert_ne!(Rng::new(1).next_u64(), Rng::new(2).next_u64());
+    }
+    #[test]
+    fn test_rng_range() {
+        let mut rng = Rng::default();
+        let mut sum = 0.0;
+        for _ in 0..10_000 {
+            let f = rng.next_f();
+            assert!((0.0..1.0).contains(&f));
+            let b = rng.bipolar();
+            assert!((-1.0..1.0).contains(&b));
+            sum += b;
+        }
+        // should average out to about zero:
+        assert!((sum / 10_000.0).abs() < 0.05);
+    }
+}
@@ -0,0 +1,168 @@
+use crate::{Phase, Sample, Scale};
+use super::{WaveCompute, WaveSteps};
+use super::minblep::{MinBlepTable, STEP_LEN};
+
+/// Trait for the ways an oscillator can produce anti-aliased output from a waveform.
+pub trait WaveBackend<W> {
+    fn compute(&mut self, wave: &W, phase: Phase, dphase: Phase, tone: Scale) -> Sample;
+
+    /// Drop any state carried over from earlier output, e.g. when the oscillator's phase is reset.
+    fn clear(&mut self) {}
+}
+
+/// Backend that anti-aliases with polyblep; cheap, and needs no state.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Polyblep;
+
+impl<W: WaveCompute> WaveBackend<W> for Polyblep {
+    fn compute(&mut self, wave: &W, phase: Phase, dphase: Phase, tone: Scale) -> Sample {
+        wave.compute_polyblep(phase, dphase, tone)
+    }
+}
+
+/// Backend that anti-aliases jumps in value with minBLEP.
+///
+/// Cleaner than [Polyblep] at high pitches, at the cost of a buffer of residuals per oscillator.
+/// Corners are still rounded off as in [WaveCompute::compute_polyblep],
+/// since their aliasing falls off much faster than that of jumps.
+///
+/// Defaulting this generates the shared [MinBlepTable] the first time through,
+/// so make sure to do that outside of processing.
+#[derive(Debug)]
+pub struct MinBlep {
+    residual: [Sample; STEP_LEN],
+    pos: usize,
+    table: &'static MinBlepTable,
+}
+
+impl Default for MinBlep {
+    fn default() -> Self {
+        Self {
+            residual: [0.0; STEP_LEN],
+            pos: 0,
+            table: MinBlepTable::get(),
+        }
+    }
+}
+
+impl MinBlep {
+    /// Drop any residuals still playing out, e.g. when the oscillator's phase is reset.
+    pub fn clear(&mut self) {
+        self.residual = [0.0; STEP_LEN];
+    }
+}
+
+impl<W: WaveSteps> WaveBackend<W> for MinBlep {
+    fn compute(&mut self, wave: &W, phase: Phase, dphase: Phase, tone: Scale) -> Sample {
+        let Self { residual, pos, table } = self;
+        let naive = wave.compute_steps(phase, dphase, tone, &mut |at, jump| {
+            // how far back the step was, if it happened since the last sample:
+            let since = if phase < at { phase + 1.0 - at } else { phase - at };
+            if since < dphase {
+                table.add_step(residual, *pos, since / dphase, jump);
+            }
+        });
+        let output = naive + residual[*pos];
+        residual[*pos] = 0.0;
+        *pos = (*pos + 1) % STEP_LEN;
+        output
+    }
+
+    fn clear(&mut self) {
+        MinBlep::clear(self);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::wave::{WaveShape, WaveSet, PdShape};
+    use super::*;
+    #[test]
+    fn test_size() {
+        assert_eq!(std::mem::size_of::<Polyblep>(), 0);
+        assert_eq!(std::mem::size_of::<MinBlep>(), 272);
+    }
+    #[test]
+    fn test_polyblep() {
+        let mut backend = Polyblep;
+        assert_eq!(backend.compute(&WaveShape::Saw, 0.95, 0.1, 0.5), WaveShape::Saw.compute_polyblep(0.95, 0.1, 0.5));
+        assert_eq!(backend.compute(&PdShape::ResoSaw, 0.95, 0.1, 0.5), PdShape::ResoSaw.compute_polyblep(0.95, 0.1, 0.5));
+    }
+    #[test]
+    fn test_minblep() {
+        let mut backend = MinBlep::default();
+        // no steps nearby, so no correction:
+        assert_eq!(backend.compute(&WaveShape::Pulse, 0.3, 0.1, 0.5), 1.0);
+        // a step just behind us, and the residual it leaves:
+        assert_eq!(backend.compute(&WaveShape::Pulse, 0.55, 0.1, 0.5), 0.9999304005362073);
+        assert_eq!(backend.compute(&WaveShape::Pulse, 0.65, 0.1, 0.5), 0.9690798749486931);
+        // clearing drops the residual:
+        backend.clear();
+        assert_eq!(backend.compute(&WaveShape::Pulse, 0.75, 0.1, 0.5), -1.0);
+        // continuous shapes just use polyblep:
+        let tri = WaveShape::Tri;
+        assert_eq!(backend.compute(&tri, 0.01, 0.1, 0.3), tri.compute_polyblep(0.01, 0.1, 0.3));
+    }
+    #[test]
+    fn test_steps() {
+        // the naive output should jump by exactly the reported height at each step:
+        fn check<W: WaveSteps>(wave: W, tone: Scale) {
+            let mut steps = vec![];
+            wave.compute_steps(0.5, 0.01, tone, &mut |at, jump| steps.push((at, jump)));
+            assert!(!steps.is_empty());
+            for (at, _) in steps.iter() {
+                let before = wave.compute_steps((at + 1.0 - 1e-9) % 1.0, 0.01, tone, &mut |_, _| ());
+                let after = wave.compute_steps(at + 1e-9, 0.01, tone, &mut |_, _| ());
+                let jump: Sample = steps.iter().filter(|(other, _)| other == at).map(|(_, jump)| jump).sum();
+                assert!((after - before - jump).abs() < 1e-6);
+            }
+        }
+        for tone in [0.1, 0.5, 0.9] {
+            check(WaveShape::Pulse, tone);
+            check(WaveShape::Saw, tone);
+            check(WaveShape::SawDown, tone);
+            check(WaveShape::Stepped, tone);
+            check(PdShape::ResoSaw, tone);
+            check(PdShape::ResoTri, tone);
+            check(PdShape::ResoTrap, tone);
+            let mut set = WaveSet::default();
+            set.set_shape(WaveShape::Saw, true);
+            set.set_shape(WaveShape::Stepped, true);
+            check(set, tone);
+        }
+    }
+    #[test]
+    fn test_minblep_aliasing() {
+        // with the fundamental on a prime bin, anything off a multiple of it is aliasing:
+        const LEN: usize = 1024;
+        const BIN: usize = 83;
+        fn aliasing<B: WaveBackend<WaveShape>>(mut backend: B, shape: WaveShape) -> f64 {
+            let dphase = BIN as Phase / LEN as Phase;
+            let mut phasor = crate::phase::Phasor::default();
+            // let any startup transients die out first:
+            for _ in 0..LEN {
+                backend.compute(&shape, phasor.advance(dphase), dphase, 0.3);
+            }
+            let out: Vec<Sample> = (0..LEN).map(|_| backend.compute(&shape, phasor.advance(dphase), dphase, 0.3)).collect();
+            let mut aliased = 0.0;
+            let mut total = 0.0;
+            for bin in 1..LEN / 2 {
+                let (mut re, mut im) = (0.0, 0.0);
+                for (i, x) in out.iter().enumerate() {
+                    let angle = 2.0 * core::f64::consts::PI * (bin * i) as f64 / LEN as f64;
+                    re += x * angle.cos();
+                    im += x * angle.sin();
+                }
+                let power = re * re + im * im;
+                total += power;
+                if bin % BIN != 0 {
+                    aliased += power;
+                }
+            }
+            aliased / total
+        }
+        for shape in [WaveShape::Saw, WaveShape::Pulse, WaveShape::Stepped] {
+            assert!(aliasing(MinBlep::default(), shape) * 100.0 < aliasing(Polyblep, shape));
+        }
+    }
+}
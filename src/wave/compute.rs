@@ -1,3 +1,22 @@
+use crate::{Phase, Scale, Sample};
+
+pub(super) fn step_height(steps: Scale) -> Sample {
+    crate::check_float_pos!(steps - 1.0);
+    2.0 / (steps - 1.0)
+}
+
+// phase plus an offset in -1..1, wrapped back into 0..1:
+fn offset(phase: Phase, offset: Phase) -> Phase {
+    let phase = phase + offset;
+    if phase >= 1.0 {
+        phase - 1.0
+    } else if phase < 0.0 {
+        phase + 1.0
+    } else {
+        phase
+    }
+}
+
 /// Raw, i.e. naive, i.e. aliasing waveforms
 pub mod raw {
     use crate::{Phase, Scale, Sample};
@@ -11,6 +30,17 @@ pub mod raw {
         }
     }
 
+    // tri with its peak moved to `skew`; 0.0 is a saw down, 0.5 a plain tri, 1.0 a saw:
+    pub fn skewed_tri(phase: Phase, skew: Scale) -> Sample {
+        crate::check_phase_bounds!(phase);
+        crate::check_float_01!(skew);
+        if phase < skew {
+            phase * 2.0 / skew - 1.0
+        } else {
+            1.0 - (phase - skew) * 2.0 / (1.0 - skew)
+        }
+    }
+
     pub fn pulse(phase: Phase, width: Scale) -> Sample {
         crate::check_phase_bounds!(phase);
         crate::check_phase_bounds!(width);
@@ -27,17 +57,117 @@ pub mod raw {
         const PI_2: f64 = core::f64::consts::PI * 2.0;
         f64::sin(PI_2 * phase)
     }
+
+    // sine with its first half squeezed into `knee` and its second half into the rest:
+    pub fn pd_sine(phase: Phase, knee: Scale) -> Sample {
+        sine(warp(phase, knee))
+    }
+
+    pub fn saw_down(phase: Phase) -> Sample {
+        crate::check_phase_bounds!(phase);
+        1.0 - phase * 2.0
+    }
+
+    // only the positive half of the (phase-distorted) sine, rescaled to fill -1..=1:
+    pub fn half_sine(phase: Phase, knee: Scale) -> Sample {
+        pd_sine(phase, knee).max(0.0) * 2.0 - 1.0
+    }
+
+    // integral of a saw, rescaled to fill -1..=1:
+    pub fn parabolic(phase: Phase) -> Sample {
+        crate::check_phase_bounds!(phase);
+        1.0 - 8.0 * phase * (1.0 - phase)
+    }
+
+    // tri, steepened so it takes `rise` to go from -1 to 1, and clipped:
+    pub fn trapezoid(phase: Phase, rise: Scale) -> Sample {
+        crate::check_float_nonneg!(rise);
+        let tri = tri(phase);
+        if rise > 0.0 {
+            (tri * (0.5 / rise)).clamp(-1.0, 1.0)
+        } else if tri < 0.0 {
+            -1.0
+        } else {
+            1.0
+        }
+    }
+
+    // rising staircase; steps should be a whole number, at least 2:
+    pub fn stepped(phase: Phase, steps: Scale) -> Sample {
+        crate::check_phase_bounds!(phase);
+        let (step, _) = crate::util::split_f(phase * steps);
+        step as Sample * super::step_height(steps) - 1.0
+    }
+
+    /// Average a wave with a copy of itself shifted by `offset` (-0.5..=0.5).
+    ///
+    /// At 0.0 this is just the wave; at +/-0.5 the copies cancel out every other harmonic.
+    pub fn dual(wave: fn(Phase) -> Sample, phase: Phase, offset: Phase) -> Sample {
+        (wave(phase) + wave(super::offset(phase, offset))) * 0.5
+    }
+
+    // phase distortion saw: a cosine whose first half is squeezed into `knee`:
+    pub fn pd_saw(phase: Phase, knee: Scale) -> Sample {
+        cosine(warp(phase, knee))
+    }
+
+    // phase distortion pulse: each half cycle ramps through half a cosine
+    // in `knee` (0..=0.5) and then holds:
+    pub fn pd_pulse(phase: Phase, knee: Scale) -> Sample {
+        crate::check_phase_bounds!(phase);
+        crate::check_float_pos!(knee);
+        let (half, phase) = if phase < 0.5 { (0.0, phase) } else { (0.5, phase - 0.5) };
+        cosine(half + (phase * 0.5 / knee).min(0.5))
+    }
+
+    // phase distortion resonance: a cosine at `ratio` times the base frequency,
+    // shaped by a window that restarts every cycle:
+    pub fn reso(phase: Phase, ratio: Scale, window: fn(Phase) -> Scale) -> Sample {
+        crate::check_phase_bounds!(phase);
+        window(phase) * cosine(phase * ratio)
+    }
+
+    // how far reso jumps when the window restarts at the start of the cycle:
+    pub fn reso_jump(ratio: Scale, window: fn(Phase) -> Scale) -> Sample {
+        window(0.0) - window(1.0) * cosine(ratio)
+    }
+
+    // unlike the other waves, takes any phase, not just 0..1:
+    fn cosine(phase: Phase) -> Sample {
+        const PI_2: f64 = core::f64::consts::PI * 2.0;
+        f64::cos(PI_2 * phase)
+    }
+
+    /// Distort phase so that `0..knee` maps to `0..0.5` and `knee..1` maps to `0.5..1`.
+    pub fn warp(phase: Phase, knee: Scale) -> Phase {
+        crate::check_phase_bounds!(phase);
+        crate::check_float_01!(knee);
+        if phase < knee {
+            phase * 0.5 / knee
+        } else {
+            0.5 + (phase - knee) * 0.5 / (1.0 - knee)
+        }
+    }
 }
 
-/// Polyblep, i.e. non-aliasing waveforms
-/// (Tri wave is still naive; working on that).
-/// We don't include a sine implementation b/c sine has no need for anti-aliasing;
+/// Polyblep, i.e. non-aliasing waveforms.
+/// We don't include a plain sine implementation b/c sine has no need for anti-aliasing;
 /// callers should always prefer the raw version.
+///
+/// Waves with jumps in value are corrected with polyblep;
+/// waves with only jumps in slope (corners) are corrected with polyblamp.
+/// Corners are kept at least `dphase` apart so the corrections don't blow up.
 pub mod polyblep {
+    use core::f64::consts::PI;
+
     use crate::{Phase, Scale, Sample};
 
-    pub fn tri(phase: Phase, _dphase: Phase) -> Sample {
-        super::raw::tri(phase)
+    // slope jumps up at the start of the cycle and back down at the peak:
+    pub fn skewed_tri(phase: Phase, dphase: Phase, skew: Scale) -> Sample {
+        let skew = keep_apart(skew, dphase);
+        let slope_diff = 2.0 / skew + 2.0 / (1.0 - skew);
+        super::raw::skewed_tri(phase, skew)
+            + slope_diff * dphase * (polyblamp(phase, dphase) - polyblamp(shift(phase, skew), dphase))
     }
 
     pub fn pulse(phase: Phase, dphase: Phase, width: Scale) -> Sample {
@@ -50,6 +180,84 @@ pub mod polyblep {
         super::raw::saw(phase) - polyblep(phase, dphase)
     }
 
+    // slope jumps by the same amount at the start of the cycle and at the knee:
+    pub fn pd_sine(phase: Phase, dphase: Phase, knee: Scale) -> Sample {
+        let knee = keep_apart(knee, dphase);
+        let slope_diff = PI / knee - PI / (1.0 - knee);
+        super::raw::pd_sine(phase, knee)
+            + slope_diff * dphase * (polyblamp(phase, dphase) + polyblamp(shift(phase, knee), dphase))
+    }
+
+    pub fn saw_down(phase: Phase, dphase: Phase) -> Sample {
+        super::raw::saw_down(phase) + polyblep(phase, dphase)
+    }
+
+    // slope jumps by 2pi/knee at both the start of the cycle and the knee:
+    pub fn half_sine(phase: Phase, dphase: Phase, knee: Scale) -> Sample {
+        let knee = keep_apart(knee, dphase);
+        super::raw::half_sine(phase, knee)
+            + 2.0 * PI / knee * dphase * (polyblamp(phase, dphase) + polyblamp(shift(phase, knee), dphase))
+    }
+
+    // slope jumps from 8 to -8 at the start of the cycle:
+    pub fn parabolic(phase: Phase, dphase: Phase) -> Sample {
+        super::raw::parabolic(phase) - 16.0 * dphase * polyblamp(phase, dphase)
+    }
+
+    // slope jumps by 2/rise at each of the four corners:
+    pub fn trapezoid(phase: Phase, dphase: Phase, rise: Scale) -> Sample {
+        let rise = rise.max(dphase);
+        let half = rise * 0.5;
+        super::raw::trapezoid(phase, rise) + 2.0 / rise * dphase * (
+            polyblamp(shift(phase, 0.25 - half), dphase)
+            - polyblamp(shift(phase, 0.25 + half), dphase)
+            - polyblamp(shift(phase, 0.75 - half), dphase)
+            + polyblamp(shift(phase, 0.75 + half), dphase)
+        )
+    }
+
+    // every step boundary (including the one at zero) goes up by the step height,
+    // except the one at zero actually goes down by 2, so we correct for it separately:
+    pub fn stepped(phase: Phase, dphase: Phase, steps: Scale) -> Sample {
+        let step_height = super::step_height(steps);
+        let (_, step_phase) = crate::util::split_f(phase * steps);
+        super::raw::stepped(phase, steps)
+            + step_height * 0.5 * polyblep(step_phase, dphase * steps)
+            - (1.0 + step_height * 0.5) * polyblep(phase, dphase)
+    }
+
+    // phase distortion saw and pulse are smooth up to their slopes, so they only need
+    // their knees kept far enough apart:
+    pub fn pd_saw(phase: Phase, dphase: Phase, knee: Scale) -> Sample {
+        super::raw::pd_saw(phase, keep_apart(knee, dphase))
+    }
+
+    pub fn pd_pulse(phase: Phase, dphase: Phase, knee: Scale) -> Sample {
+        super::raw::pd_pulse(phase, knee.max(dphase).min(0.5))
+    }
+
+    // the window restarting at the start of the cycle makes a jump
+    // from wherever the window left off back to its start:
+    pub fn reso(phase: Phase, dphase: Phase, ratio: Scale, window: fn(Phase) -> Scale) -> Sample {
+        super::raw::reso(phase, ratio, window)
+            + super::raw::reso_jump(ratio, window) * 0.5 * polyblep(phase, dphase)
+    }
+
+    /// Polyblep version of [super::raw::dual].
+    pub fn dual(wave: fn(Phase, Phase) -> Sample, phase: Phase, dphase: Phase, offset: Phase) -> Sample {
+        (wave(phase, dphase) + wave(super::offset(phase, offset), dphase)) * 0.5
+    }
+
+    // phase relative to a discontinuity at offset:
+    fn shift(phase: Phase, offset: Phase) -> Phase {
+        (phase + 1.0 - offset) % 1.0
+    }
+
+    // keep a corner at least dphase away from the corner at zero:
+    fn keep_apart(corner: Phase, dphase: Phase) -> Phase {
+        corner.max(dphase).min(1.0 - dphase)
+    }
+
     // dphase = freq / sample_rate i.e. the oscillator inc value?
     // the idea is to subtract this value from the naive oscillator output
     // and it should work for saw waves just like that.
@@ -69,6 +277,21 @@ pub mod polyblep {
             0.0
         }
     }
+
+    // integrated polyblep, for corners rather than jumps.
+    // callers should scale it by the change in slope times dphase.
+    fn polyblamp(mut phase: Scale, dphase: Phase) -> Sample {
+        crate::check_float_pos!(dphase);
+        if phase < dphase {
+            phase = 1.0 - phase / dphase;
+            phase * phase * phase / 6.0
+        } else if phase > 1.0 - dphase {
+            phase = (phase - 1.0) / dphase + 1.0;
+            phase * phase * phase / 6.0
+        } else {
+            0.0
+        }
+    }
 }
 
 #[cfg(test)]
@@ -82,8 +305,9 @@ mod test {
         assert_eq!(raw::tri(0.5), 1.0);
         assert_eq!(raw::tri(0.75), 0.0);
         assert_eq!(raw::tri(0.99), -0.96);
-        // polyblep tri currently just fwds to raw:
-        assert_eq!(raw::tri(0.99), polyblep::tri(0.99, 0.1));
+        // skewed tri is the same as tri when centered:
+        assert_eq!(raw::skewed_tri(0.3, 0.5), raw::tri(0.3));
+        assert_eq!(raw::skewed_tri(0.99, 0.5), raw::tri(0.99));
         // raw pulse
         assert_eq!(raw::pulse(0.0, 0.5), 1.0);
         assert_eq!(raw::pulse(0.25, 0.5), 1.0);
@@ -121,4 +345,114 @@ mod test {
         assert_eq!(polyblep::saw(0.95, 0.01), 0.8999999999999999);
         assert_eq!(polyblep::saw(0.95, 0.1), 0.6500000000000004);
     }
+    #[test]
+    fn test_extended_waves_compute() {
+        // raw saw down
+        assert_eq!(raw::saw_down(0.0), 1.0);
+        assert_eq!(raw::saw_down(0.5), 0.0);
+        assert_eq!(raw::saw_down(0.9), -0.8);
+        // raw half sine
+        assert_eq!(raw::half_sine(0.0, 0.5), -1.0);
+        assert_eq!(raw::half_sine(0.25, 0.5), 1.0);
+        assert_eq!(raw::half_sine(0.75, 0.5), -1.0);
+        // raw parabolic
+        assert_eq!(raw::parabolic(0.0), 1.0);
+        assert_eq!(raw::parabolic(0.25), -0.5);
+        assert_eq!(raw::parabolic(0.5), -1.0);
+        // raw trapezoid
+        assert_eq!(raw::trapezoid(0.0, 0.25), -1.0);
+        assert_eq!(raw::trapezoid(0.25, 0.25), 0.0);
+        assert_eq!(raw::trapezoid(0.3, 0.25), 0.3999999999999999);
+        assert_eq!(raw::trapezoid(0.5, 0.25), 1.0);
+        assert_eq!(raw::trapezoid(0.75, 0.25), 0.0);
+        // raw stepped
+        assert_eq!(raw::stepped(0.0, 4.0), -1.0);
+        assert_eq!(raw::stepped(0.3, 4.0), -0.33333333333333337);
+        assert_eq!(raw::stepped(0.6, 4.0), 0.33333333333333326);
+        assert_eq!(raw::stepped(0.99, 4.0), 1.0);
+
+        // polyblep saw down, close to zero and one:
+        assert_eq!(polyblep::saw_down(0.05, 0.01), 0.9);
+        assert_eq!(polyblep::saw_down(0.05, 0.1), 0.65);
+        assert_eq!(polyblep::saw_down(0.95, 0.1), -0.6500000000000004);
+
+        // polyblamp waves should match raw when far from a corner...
+        assert_eq!(polyblep::half_sine(0.25, 0.1, 0.5), raw::half_sine(0.25, 0.5));
+        assert_eq!(polyblep::parabolic(0.5, 0.1), raw::parabolic(0.5));
+        assert_eq!(polyblep::trapezoid(0.25, 0.1, 0.25), raw::trapezoid(0.25, 0.25));
+        // ...and round off the corners when close:
+        assert_eq!(polyblep::half_sine(0.01, 0.1, 0.5), -0.7217375579769093);
+        assert_eq!(polyblep::parabolic(0.01, 0.1), 0.7263999999999999);
+        assert_eq!(polyblep::trapezoid(0.12, 0.1, 0.25), -0.8856833333333329);
+
+        // polyblep stepped, close to an inner step and close to the wrap:
+        assert_eq!(polyblep::stepped(0.2, 0.01, 4.0), raw::stepped(0.2, 4.0));
+        assert_eq!(polyblep::stepped(0.26, 0.1, 4.0), -0.6033333333333333);
+        assert_eq!(polyblep::stepped(0.99, 0.1, 4.0), 0.1900000000000004);
+    }
+    #[test]
+    fn test_tone_compute() {
+        // skewed tri, towards either saw:
+        assert_eq!(raw::skewed_tri(0.1, 0.2), 0.0);
+        assert_eq!(raw::skewed_tri(0.2, 0.2), 1.0);
+        assert_eq!(raw::skewed_tri(0.8, 0.2), -0.5000000000000002);
+        assert_eq!(raw::skewed_tri(0.5, 0.0), raw::saw_down(0.5));
+        assert_eq!(raw::skewed_tri(0.5, 1.0), raw::saw(0.5));
+        // corners get rounded off:
+        assert_eq!(polyblep::skewed_tri(0.5, 0.01, 0.2), raw::skewed_tri(0.5, 0.2));
+        assert_eq!(polyblep::skewed_tri(0.19, 0.1, 0.2), 0.7481249999999999);
+        // even when skewed all the way, where the corner turns into a jump:
+        assert_eq!(polyblep::skewed_tri(0.99, 0.1, 1.0), polyblep::skewed_tri(0.99, 0.1, 0.9));
+
+        // phase-distorted sine:
+        assert_eq!(raw::pd_sine(0.3, 0.5), raw::sine(0.3));
+        assert_eq!(raw::pd_sine(0.1, 0.2), raw::sine(0.25));
+        assert_eq!(raw::pd_sine(0.6, 0.2), raw::sine(0.75));
+        assert_eq!(polyblep::pd_sine(0.99, 0.1, 0.5), raw::sine(0.99));
+        assert_eq!(polyblep::pd_sine(0.19, 0.1, 0.2), 0.2995732803194159);
+        assert_eq!(raw::half_sine(0.1, 0.2), 1.0);
+        assert_eq!(polyblep::half_sine(0.19, 0.1, 0.2), -0.3054275625083784);
+
+        // dual waves:
+        assert_eq!(raw::dual(raw::saw, 0.3, 0.0), raw::saw(0.3));
+        // half a cycle away gives a half-volume saw an octave up:
+        assert_eq!(raw::dual(raw::saw, 0.3, 0.5), 0.10000000000000003);
+        assert_eq!(raw::dual(raw::saw, 0.3, -0.5), raw::dual(raw::saw, 0.3, 0.5));
+        assert_eq!(polyblep::dual(polyblep::saw, 0.3, 0.1, 0.0), polyblep::saw(0.3, 0.1));
+        // the shifted copy's jump gets corrected too:
+        assert_eq!(polyblep::dual(polyblep::saw, 0.45, 0.1, 0.5), 0.2750000000000002);
+
+        // trapezoid from square to tri:
+        assert_eq!(raw::trapezoid(0.2, 0.0), -1.0);
+        assert_eq!(raw::trapezoid(0.3, 0.0), 1.0);
+        assert_eq!(raw::trapezoid(0.3, 0.5), raw::tri(0.3));
+        assert_eq!(polyblep::trapezoid(0.3, 0.1, 0.5), polyblep::skewed_tri(0.3, 0.1, 0.5));
+        assert_eq!(polyblep::trapezoid(0.22, 0.1, 0.0), -0.43200000000000005);
+
+        // stepped with fewer and more steps:
+        assert_eq!(raw::stepped(0.4, 2.0), -1.0);
+        assert_eq!(raw::stepped(0.6, 2.0), 1.0);
+        assert_eq!(raw::stepped(0.6, 8.0), 0.1428571428571428);
+        assert_eq!(polyblep::stepped(0.52, 0.1, 2.0), -polyblep::pulse(0.52, 0.1, 0.5));
+    }
+    #[test]
+    fn test_pd_waves_compute() {
+        // pd saw and pulse are plain cosines with their knees at the middle:
+        assert_eq!(raw::pd_saw(0.0, 0.5), 1.0);
+        assert_eq!(raw::pd_saw(0.5, 0.5), -1.0);
+        assert_eq!(raw::pd_pulse(0.5, 0.5), -1.0);
+        // moving the knee moves the bottom of the cosine:
+        assert_eq!(raw::pd_saw(0.2, 0.2), -1.0);
+        // pd pulse holds after the knee:
+        assert_eq!(raw::pd_pulse(0.2, 0.1), -1.0);
+        assert_eq!(raw::pd_pulse(0.4, 0.1), -1.0);
+        assert_eq!(raw::pd_pulse(0.7, 0.1), 1.0);
+        // reso is just a windowed cosine:
+        assert_eq!(raw::reso(0.25, 2.0, |phase| 1.0 - phase), -0.75);
+        // polyblep reso corrects the jump from the end of the window back to the start:
+        assert_eq!(polyblep::reso(0.5, 0.1, 2.0, |phase| 1.0 - phase), raw::reso(0.5, 2.0, |phase| 1.0 - phase));
+        assert_eq!(polyblep::reso(0.05, 0.1, 2.0, |phase| 1.0 - phase), 0.6435661446562);
+        // no jump, no correction:
+        assert_eq!(polyblep::reso(0.05, 0.1, 2.0, |_| 1.0), raw::reso(0.05, 2.0, |_| 1.0));
+    }
 }
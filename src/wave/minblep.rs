@@ -0,0 +1,181 @@
+use std::fmt;
+use std::sync::OnceLock;
+
+use core::f64::consts::PI;
+
+use crate::{F, Sample};
+
+/// Length, in output samples, of the residual left by a single step.
+pub const STEP_LEN: usize = ZERO_CROSSINGS * 2;
+
+// zero crossings of the sinc on each side of its peak:
+const ZERO_CROSSINGS: usize = 16;
+// table entries per output sample:
+const OVERSAMPLE: usize = 32;
+const TABLE_LEN: usize = STEP_LEN * OVERSAMPLE;
+// the cepstrum needs a fair bit of zero-padding to stay accurate:
+const FFT_LEN: usize = TABLE_LEN * 4;
+
+static TABLE: OnceLock<MinBlepTable> = OnceLock::new();
+
+/// Precomputed residual of a minimum-phase band-limited step, i.e. minBLEP minus an ideal step.
+///
+/// There is only ever one table, generated the first time it's asked for.
+pub struct MinBlepTable {
+    residual: [Sample; TABLE_LEN + 1],
+}
+
+impl MinBlepTable {
+    /// Get the shared table, generating it if this is the first call.
+    ///
+    /// Generating the table allocates, so make sure this is first called outside of processing.
+    pub fn get() -> &'static Self {
+        TABLE.get_or_init(Self::generate)
+    }
+
+    /// Add the residual of a step of height `jump` into the ring buffer `buf`,
+    /// starting at index `pos`, for a step that happened `frac` samples ago (0..1).
+    pub fn add_step(&self, buf: &mut [Sample; STEP_LEN], pos: usize, frac: F, jump: Sample) {
+        crate::check_float_01!(frac);
+        for i in 0..STEP_LEN {
+            let (idx, frac) = crate::util::split_f((i as F + frac) * OVERSAMPLE as F);
+            let residual = crate::util::lirp(self.residual[idx], self.residual[idx + 1], frac);
+            buf[(pos + i) % STEP_LEN] += jump * residual;
+        }
+    }
+
+    fn generate() -> Self {
+        // blackman-windowed sinc:
+        let mut buf = vec![(0.0, 0.0); FFT_LEN];
+        for (i, x) in buf.iter_mut().take(TABLE_LEN + 1).enumerate() {
+            let t = (i as F - (TABLE_LEN / 2) as F) / OVERSAMPLE as F;
+            let sinc = if t == 0.0 { 1.0 } else { F::sin(PI * t) / (PI * t) };
+            let w = i as F / TABLE_LEN as F;
+            let window = 0.42 - 0.5 * F::cos(2.0 * PI * w) + 0.08 * F::cos(4.0 * PI * w);
+            x.0 = sinc * window;
+        }
+        min_phase(&mut buf);
+        // integrate the impulse into a step, and normalize it to end at 1:
+        let mut residual = [0.0; TABLE_LEN + 1];
+        let mut sum = 0.0;
+        for (r, x) in residual.iter_mut().zip(buf.iter()) {
+            sum += x.0;
+            *r = sum;
+        }
+        for r in residual.iter_mut() {
+            *r = *r / sum - 1.0;
+        }
+        // whatever's left at the very end would click when the residual runs out:
+        residual[TABLE_LEN] = 0.0;
+        Self { residual }
+    }
+}
+
+impl fmt::Debug for MinBlepTable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MinBlepTable").finish_non_exhaustive()
+    }
+}
+
+type Complex = (F, F);
+
+// convert a real signal to minimum phase, by way of its real cepstrum:
+fn min_phase(buf: &mut [Complex]) {
+    let len = buf.len();
+    fft(buf, false);
+    for x in buf.iter_mut() {
+        *x = (F::hypot(x.0, x.1).max(1e-100).ln(), 0.0);
+    }
+    fft(buf, true);
+    // fold the cepstrum onto its causal half:
+    for x in buf[1..len / 2].iter_mut() {
+        *x = (x.0 * 2.0, 0.0);
+    }
+    for x in buf[len / 2 + 1..].iter_mut() {
+        *x = (0.0, 0.0);
+    }
+    fft(buf, false);
+    for x in buf.iter_mut() {
+        let mag = F::exp(x.0);
+        *x = (mag * F::cos(x.1), mag * F::sin(x.1));
+    }
+    fft(buf, true);
+}
+
+// in-place radix-2 fft; inverse is normalized. buf's length must be a power of 2:
+fn fft(buf: &mut [Complex], inverse: bool) {
+    let len = buf.len();
+    debug_assert!(len.is_power_of_two());
+    let mut j = 0;
+    for i in 1..len {
+        let mut bit = len >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            buf.swap(i, j);
+        }
+    }
+    let sign = if inverse { 1.0 } else { -1.0 };
+    let mut size = 2;
+    while size <= len {
+        let angle = sign * 2.0 * PI / size as F;
+        for start in (0..len).step_by(size) {
+            for k in 0..size / 2 {
+                let (wr, wi) = (F::cos(angle * k as F), F::sin(angle * k as F));
+                let (ar, ai) = buf[start + k];
+                let (br, bi) = buf[start + k + size / 2];
+                let (tr, ti) = (br * wr - bi * wi, br * wi + bi * wr);
+                buf[start + k] = (ar + tr, ai + ti);
+                buf[start + k + size / 2] = (ar - tr, ai - ti);
+            }
+        }
+        size <<= 1;
+    }
+    if inverse {
+        for x in buf.iter_mut() {
+            *x = (x.0 / len as F, x.1 / len as F);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn test_fft() {
+        // an impulse has a flat spectrum:
+        let mut buf = [(0.0, 0.0); 8];
+        buf[0] = (1.0, 0.0);
+        fft(&mut buf, false);
+        assert!(buf.iter().all(|&x| x == (1.0, 0.0)));
+        // and back again:
+        fft(&mut buf, true);
+        assert_eq!(buf[0], (1.0, 0.0));
+        assert!(buf[1..].iter().all(|&x| x == (0.0, 0.0)));
+    }
+    #[test]
+    fn test_table() {
+        let table = MinBlepTable::get();
+        // the residual should start out undoing the whole step, and fade out to nothing:
+        assert!((table.residual[0] + 1.0).abs() < 1e-3);
+        assert!(table.residual[TABLE_LEN - 1].abs() < 1e-3);
+        assert_eq!(table.residual[TABLE_LEN], 0.0);
+        // and it should be the same table every time:
+        assert!(std::ptr::eq(table, MinBlepTable::get()));
+    }
+    #[test]
+    fn test_add_step() {
+        let table = MinBlepTable::get();
+        let mut buf = [0.0; STEP_LEN];
+        table.add_step(&mut buf, 0, 0.0, 2.0);
+        assert_eq!(buf[0], table.residual[0] * 2.0);
+        assert_eq!(buf[1], table.residual[OVERSAMPLE] * 2.0);
+        // residuals overlap, wrapping around the ring:
+        table.add_step(&mut buf, STEP_LEN - 1, 0.5, -1.0);
+        assert_eq!(buf[0], -1.0154603813735084);
+        assert_eq!(buf[STEP_LEN - 1], 0.9999552220342449);
+    }
+}
@@ -8,18 +8,43 @@ mod wave_set;
 pub use wave_set::WaveSet;
 
 mod wave_shape;
-pub use wave_shape::WaveShape;
+pub use wave_shape::{WaveShape, InvalidWaveShape};
+
+mod pd_shape;
+pub use pd_shape::PdShape;
+
+mod backend;
+pub use backend::{WaveBackend, Polyblep, MinBlep};
+
+mod minblep;
+pub use minblep::MinBlepTable;
 
 const TRI: u8 = 0;
 const PULSE: u8 = 1;
 const SAW: u8 = 2;
 const SINE: u8 = 3;
+const SAW_DOWN: u8 = 4;
+const HALF_SINE: u8 = 5;
+const PARABOLIC: u8 = 6;
+const TRAPEZOID: u8 = 7;
+const STEPPED: u8 = 8;
 
-const SHAPES: [WaveShape; 4] =
-    [WaveShape::Tri, WaveShape::Pulse, WaveShape::Saw, WaveShape::Sine];
+const SHAPES: [WaveShape; 9] = [
+    WaveShape::Tri, WaveShape::Pulse, WaveShape::Saw, WaveShape::Sine,
+    WaveShape::SawDown, WaveShape::HalfSine, WaveShape::Parabolic, WaveShape::Trapezoid,
+    WaveShape::Stepped,
+];
 
 /// Trait for values that act like waveforms.
 pub trait WaveCompute {
     fn compute_aliasing(&self, phase: Phase, tone: Scale) -> Sample;
     fn compute_polyblep(&self, phase: Phase, dphase: Phase, tone: Scale) -> Sample;
 }
+
+/// Trait for waveforms that can report their jumps in value, for backends like [MinBlep].
+pub trait WaveSteps: WaveCompute {
+    /// Compute output with any jumps in value left in,
+    /// passing the phase and height of each jump in the cycle to `step`.
+    /// Corners may still be rounded off as in [WaveCompute::compute_polyblep].
+    fn compute_steps(&self, phase: Phase, dphase: Phase, tone: Scale, step: &mut dyn FnMut(Phase, Sample)) -> Sample;
+}
@@ -0,0 +1,152 @@
+use crate::{Phase, Sample, Scale};
+use super::{compute, WaveCompute, WaveSteps};
+
+// knee never quite reaches zero, so the raw waves stay finite at full distortion:
+const MAX_KNEE_SHIFT: Scale = 0.49;
+// ratio of the resonant cosine to the base frequency at full distortion:
+const MAX_RESONANCE: Scale = 16.0;
+
+/// Enum for phase distortion waveforms, in the style of the Casio CZ series.
+///
+/// Each shape reads a cosine through a distorted phase,
+/// using `tone` (0..=1) as the distortion amount.
+/// A tone of 0.0 gives a plain cosine for [PdShape::Saw] and [PdShape::Pulse],
+/// and the least resonant version of the resonant shapes.
+#[derive(Debug, Clone, Copy, Default)]
+pub enum PdShape {
+    /// Cosine with its first half squeezed, sharpening towards a saw.
+    #[default]
+    Saw,
+    /// Cosine that holds at the top and bottom of each half cycle, sharpening towards a square.
+    Pulse,
+    /// Resonant cosine under a falling saw window.
+    ResoSaw,
+    /// Resonant cosine under a window that falls to zero mid-cycle and rises back.
+    ResoTri,
+    /// Resonant cosine under a window that holds for half a cycle and then falls.
+    ResoTrap,
+}
+
+impl WaveCompute for PdShape {
+    fn compute_aliasing(&self, phase: Phase, tone: Scale) -> Sample {
+        use compute::raw::*;
+        match self {
+            Self::Saw      => pd_saw(phase, knee(tone)),
+            Self::Pulse    => pd_pulse(phase, knee(tone)),
+            Self::ResoSaw  => reso(phase, resonance(tone), saw_window),
+            Self::ResoTri  => reso(phase, resonance(tone), tri_window),
+            Self::ResoTrap => reso(phase, resonance(tone), trap_window),
+        }
+    }
+
+    fn compute_polyblep(&self, phase: Phase, dphase: Phase, tone: Scale) -> Sample {
+        use compute::polyblep::*;
+        match self {
+            Self::Saw      => pd_saw(phase, dphase, knee(tone)),
+            Self::Pulse    => pd_pulse(phase, dphase, knee(tone)),
+            Self::ResoSaw  => reso(phase, dphase, resonance(tone), saw_window),
+            Self::ResoTri  => reso(phase, dphase, resonance(tone), tri_window),
+            Self::ResoTrap => reso(phase, dphase, resonance(tone), trap_window),
+        }
+    }
+}
+
+impl WaveSteps for PdShape {
+    fn compute_steps(&self, phase: Phase, dphase: Phase, tone: Scale, step: &mut dyn FnMut(Phase, Sample)) -> Sample {
+        use compute::raw::*;
+        let window = match self {
+            // saw and pulse are continuous:
+            Self::Saw | Self::Pulse => return self.compute_polyblep(phase, dphase, tone),
+            Self::ResoSaw  => saw_window,
+            Self::ResoTri  => tri_window,
+            Self::ResoTrap => trap_window,
+        };
+        step(0.0, reso_jump(resonance(tone), window));
+        reso(phase, resonance(tone), window)
+    }
+}
+
+// these map tone onto each shape's own parameter:
+
+fn knee(tone: Scale) -> Scale {
+    0.5 - tone * MAX_KNEE_SHIFT
+}
+
+fn resonance(tone: Scale) -> Scale {
+    1.0 + tone * (MAX_RESONANCE - 1.0)
+}
+
+// windows for the resonant shapes:
+
+fn saw_window(phase: Phase) -> Scale {
+    1.0 - phase
+}
+
+fn tri_window(phase: Phase) -> Scale {
+    (1.0 - phase * 2.0).abs()
+}
+
+fn trap_window(phase: Phase) -> Scale {
+    ((1.0 - phase) * 2.0).min(1.0)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn test_size() {
+        assert_eq!(std::mem::size_of::<PdShape>(), 1);
+    }
+    #[test]
+    fn test_default() {
+        assert!(matches!(PdShape::default(), PdShape::Saw));
+    }
+    #[test]
+    fn test_compute_aliasing() {
+        // no distortion gives a plain cosine:
+        let cosine = |phase: Phase| f64::cos(core::f64::consts::PI * 2.0 * phase);
+        assert_eq!(PdShape::Saw.compute_aliasing(0.3, 0.0), cosine(0.3));
+        assert_eq!(PdShape::Pulse.compute_aliasing(0.3, 0.0), cosine(0.3));
+        assert_eq!(PdShape::Pulse.compute_aliasing(0.7, 0.0), cosine(0.7));
+        // full distortion sharpens the cosine into a saw-like ramp and a near-square:
+        assert_eq!(PdShape::Saw.compute_aliasing(0.3, 1.0), -0.6056096871376674);
+        assert_eq!(PdShape::Pulse.compute_aliasing(0.3, 1.0), -1.0);
+        assert_eq!(PdShape::Pulse.compute_aliasing(0.8, 1.0), 1.0);
+        // resonant shapes follow their windows:
+        assert_eq!(PdShape::ResoSaw.compute_aliasing(0.0, 0.5), 1.0);
+        assert_eq!(PdShape::ResoSaw.compute_aliasing(0.75, 0.0), -4.592425496802574e-17);
+        assert_eq!(PdShape::ResoTri.compute_aliasing(0.5, 0.7), -0.0);
+        assert_eq!(PdShape::ResoTrap.compute_aliasing(0.4, 1.0), -0.8090169943749465);
+        assert_eq!(PdShape::ResoTrap.compute_aliasing(0.9, 1.0), -0.16180339887498904);
+    }
+    #[test]
+    fn test_compute_polyblep() {
+        use compute::{raw, polyblep};
+        assert_eq!(PdShape::Saw.compute_polyblep(0.3, 0.01, 0.5), raw::pd_saw(0.3, knee(0.5)));
+        assert_eq!(PdShape::Pulse.compute_polyblep(0.3, 0.01, 0.5), raw::pd_pulse(0.3, knee(0.5)));
+        // knees are kept at least dphase wide:
+        assert_eq!(PdShape::Saw.compute_polyblep(0.03, 0.1, 1.0), raw::pd_saw(0.03, 0.1));
+        assert_eq!(PdShape::Pulse.compute_polyblep(0.03, 0.1, 1.0), raw::pd_pulse(0.03, 0.1));
+        // resonant shapes only differ from raw close to the window reset:
+        for shape in [PdShape::ResoSaw, PdShape::ResoTri, PdShape::ResoTrap] {
+            assert_eq!(shape.compute_polyblep(0.5, 0.01, 0.3), shape.compute_aliasing(0.5, 0.3));
+            assert_ne!(shape.compute_polyblep(0.995, 0.01, 0.3), shape.compute_aliasing(0.995, 0.3));
+            assert_ne!(shape.compute_polyblep(0.005, 0.01, 0.3), shape.compute_aliasing(0.005, 0.3));
+        }
+        assert_eq!(
+            PdShape::ResoSaw.compute_polyblep(0.995, 0.01, 0.3),
+            polyblep::reso(0.995, 0.01, resonance(0.3), saw_window),
+        );
+        // whole-number ratios make the tri window seamless:
+        assert_eq!(PdShape::ResoTri.compute_polyblep(0.005, 0.01, 0.0), PdShape::ResoTri.compute_aliasing(0.005, 0.0));
+    }
+    #[test]
+    fn test_reso_jump() {
+        // the polyblep corrections either side of the reset should meet in the middle:
+        for shape in [PdShape::ResoSaw, PdShape::ResoTri, PdShape::ResoTrap] {
+            let before = shape.compute_polyblep(1.0 - 1e-9, 0.01, 0.3);
+            let after = shape.compute_polyblep(0.0, 0.01, 0.3);
+            assert!((before - after).abs() < 1e-6);
+        }
+    }
+}
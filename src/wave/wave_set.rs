@@ -1,17 +1,17 @@
 use crate::{Sample, Scale, Phase};
 use crate::util::Bitmask;
 
-use super::{WaveShape, WaveCompute, SHAPES};
+use super::{WaveShape, WaveCompute, WaveSteps, SHAPES};
 
 // in our synth implementations, we noticed that the perceived volume of the different
 // wave shapes was vastly different, so we added these constants to normalize them.
 // TODO these should really be passed in by the caller rather than hardcoded, though.
-const SHAPE_VOLUMES: [Scale; 4] = [1.0, 0.7, 0.6, 1.0];
+const SHAPE_VOLUMES: [Scale; 9] = [1.0, 0.7, 0.6, 1.0, 0.6, 0.9, 1.0, 0.8, 0.7];
 
 /// A set of [WaveShape]s that can compute multiple waveform outputs at once.
 #[derive(Debug, Default, Clone)]
 pub struct WaveSet {
-    shapes: u8,
+    shapes: u16,
 }
 
 impl WaveSet {
@@ -47,21 +47,39 @@ impl WaveCompute for WaveSet {
     }
 }
 
+impl WaveSteps for WaveSet {
+    fn compute_steps(&self, phase: Phase, dphase: Phase, tone: Scale, step: &mut dyn FnMut(Phase, Sample)) -> Sample {
+        let mut output = 0.0;
+        for i in 0..SHAPES.len() {
+            let shape = SHAPES[i];
+            if self.has_shape(shape) {
+                let volume = SHAPE_VOLUMES[i];
+                output += shape.compute_steps(phase, dphase, tone, &mut |at, jump| step(at, jump * volume)) * volume;
+            }
+        }
+        output
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
-    use super::super::{SAW, PULSE, TRI, SINE};
+    use super::super::{SAW, PULSE, TRI, SINE, STEPPED};
     #[test]
     fn test_size() {
-        assert_eq!(std::mem::size_of::<WaveSet>(), 1);
+        assert_eq!(std::mem::size_of::<WaveSet>(), 2);
     }
     #[test]
     fn test_has_shape() {
-        let mut set = WaveSet { shapes: u8::only_bit(PULSE as usize) };
+        let mut set = WaveSet { shapes: u16::only_bit(PULSE as usize) };
         assert!(set.has_shape(WaveShape::Pulse));
         assert!(!set.has_shape(WaveShape::Saw));
         set.shapes.set_bit(SAW as usize);
         assert!(set.has_shape(WaveShape::Saw));
+        // shapes past the first 8 need the wider mask:
+        assert!(!set.has_shape(WaveShape::Stepped));
+        set.shapes.set_bit(STEPPED as usize);
+        assert!(set.has_shape(WaveShape::Stepped));
     }
     #[test]
     fn test_set_shape() {
@@ -71,6 +89,9 @@ mod test {
         assert!(set.has_shape(WaveShape::Sine));
         set.set_shape(WaveShape::Sine, false);
         assert!(!set.has_shape(WaveShape::Sine));
+        set.set_shape(WaveShape::Stepped, true);
+        assert!(set.has_shape(WaveShape::Stepped));
+        assert!(!set.has_shape(WaveShape::Sine));
     }
     #[test]
     fn test_compute_aliasing() {
@@ -94,6 +115,12 @@ mod test {
 
         set.set_shape(WaveShape::Saw, true);
         assert_eq!(set.compute_polyblep(0.95, 0.1, 0.5),
-            pulse_out * SHAPE_VOLUMES[PULSE as usize] + saw_out * SHAPE_VOLUMES[SAW as usize])
+            pulse_out * SHAPE_VOLUMES[PULSE as usize] + saw_out * SHAPE_VOLUMES[SAW as usize]);
+
+        let stepped_out = WaveShape::Stepped.compute_polyblep(0.95, 0.1, 0.5);
+        set.set_shape(WaveShape::Stepped, true);
+        assert_eq!(set.compute_polyblep(0.95, 0.1, 0.5),
+            pulse_out * SHAPE_VOLUMES[PULSE as usize] + saw_out * SHAPE_VOLUMES[SAW as usize]
+                + stepped_out * SHAPE_VOLUMES[STEPPED as usize]);
     }
 }
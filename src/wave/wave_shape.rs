@@ -1,61 +1,161 @@
+use std::fmt;
+
 use crate::{Phase, Sample, Scale};
-use super::{compute, WaveCompute, TRI, PULSE, SAW, SINE};
+use super::{compute, WaveCompute, WaveSteps, SHAPES};
+use super::{TRI, PULSE, SAW, SINE, SAW_DOWN, HALF_SINE, PARABOLIC, TRAPEZOID, STEPPED};
 
 /// Enum for basic waveform types.
-#[derive(Debug, Clone, Copy)]
+///
+/// Each shape uses `tone` (0..=1) as a timbre control, as documented on each variant.
+/// A tone of 0.5 always gives the plain version of the shape.
+#[derive(Debug, Clone, Copy, Default)]
 pub enum WaveShape {
-    Tri, Pulse, Saw, Sine,
-}
-
-impl Default for WaveShape {
-    fn default() -> Self { Self::Tri }
+    /// Tone moves the peak, from saw down (0.0) through tri (0.5) to saw (1.0).
+    #[default]
+    Tri,
+    /// Tone is the pulse width.
+    Pulse,
+    /// Tone mixes in a phase-shifted copy, up to half a cycle away at 0.0 and 1.0
+    /// (like a PWM saw), which morphs towards a saw an octave up.
+    Saw,
+    /// Tone distorts the phase, squeezing the first half of the cycle (below 0.5)
+    /// or the second half (above 0.5).
+    Sine,
+    /// Ramp-down saw; tone works as for [WaveShape::Saw].
+    SawDown,
+    /// Half-rectified sine; tone works as for [WaveShape::Sine].
+    HalfSine,
+    /// Tone works as for [WaveShape::Saw].
+    Parabolic,
+    /// Tone sets the steepness of the sides, from square (0.0) to tri (1.0).
+    Trapezoid,
+    /// Staircase; tone sets the number of steps, from 2 (0.0) through 4 (0.5) to 8 (1.0).
+    Stepped,
 }
 
 impl WaveShape {
     pub const fn into_u8(self) -> u8 {
         match self {
-            Self::Tri   => TRI,
-            Self::Pulse => PULSE,
-            Self::Saw   => SAW,
-            Self::Sine  => SINE,
+            Self::Tri       => TRI,
+            Self::Pulse     => PULSE,
+            Self::Saw       => SAW,
+            Self::Sine      => SINE,
+            Self::SawDown   => SAW_DOWN,
+            Self::HalfSine  => HALF_SINE,
+            Self::Parabolic => PARABOLIC,
+            Self::Trapezoid => TRAPEZOID,
+            Self::Stepped   => STEPPED,
         }
     }
 
     pub const fn into_usize(self) -> usize {
         self.into_u8() as usize
     }
+}
 
-    pub fn from_u8(idx: u8) -> Self {
-        match idx {
-            TRI     => Self::Tri,
-            PULSE   => Self::Pulse,
-            SAW     => Self::Saw,
-            SINE    => Self::Sine,
-            _ => unimplemented!("WaveShape index > 3"),
-        }
+impl TryFrom<u8> for WaveShape {
+    type Error = InvalidWaveShape;
+    fn try_from(idx: u8) -> Result<Self, Self::Error> {
+        SHAPES.get(idx as usize).copied().ok_or(InvalidWaveShape(idx))
+    }
+}
+
+/// Error returned when converting an out-of-range index into a [WaveShape].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InvalidWaveShape(pub u8);
+
+impl fmt::Display for InvalidWaveShape {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "WaveShape index {} is out of range", self.0)
     }
 }
 
+impl std::error::Error for InvalidWaveShape {}
+
 impl WaveCompute for WaveShape {
     fn compute_aliasing(&self, phase: Phase, tone: Scale) -> Sample {
+        use compute::raw::*;
         match self {
-            Self::Tri   => compute::raw::tri(phase),
-            Self::Pulse => compute::raw::pulse(phase, tone),
-            Self::Saw   => compute::raw::saw(phase),
-            Self::Sine  => compute::raw::sine(phase),
+            Self::Tri       => skewed_tri(phase, tone),
+            Self::Pulse     => pulse(phase, tone),
+            Self::Saw       => dual(saw, phase, dual_offset(tone)),
+            Self::Sine      => pd_sine(phase, tone),
+            Self::SawDown   => dual(saw_down, phase, dual_offset(tone)),
+            Self::HalfSine  => half_sine(phase, tone),
+            Self::Parabolic => dual(parabolic, phase, dual_offset(tone)),
+            Self::Trapezoid => trapezoid(phase, trapezoid_rise(tone)),
+            Self::Stepped   => stepped(phase, stepped_steps(tone)),
         }
     }
 
     fn compute_polyblep(&self, phase: Phase, dphase: Phase, tone: Scale) -> Sample {
+        use compute::polyblep::*;
+        match self {
+            Self::Tri       => skewed_tri(phase, dphase, tone),
+            Self::Pulse     => pulse(phase, dphase, tone),
+            Self::Saw       => dual(saw, phase, dphase, dual_offset(tone)),
+            Self::Sine      => pd_sine(phase, dphase, tone),
+            Self::SawDown   => dual(saw_down, phase, dphase, dual_offset(tone)),
+            Self::HalfSine  => half_sine(phase, dphase, tone),
+            Self::Parabolic => dual(parabolic, phase, dphase, dual_offset(tone)),
+            Self::Trapezoid => trapezoid(phase, dphase, trapezoid_rise(tone)),
+            Self::Stepped   => stepped(phase, dphase, stepped_steps(tone)),
+        }
+    }
+}
+
+impl WaveSteps for WaveShape {
+    fn compute_steps(&self, phase: Phase, dphase: Phase, tone: Scale, step: &mut dyn FnMut(Phase, Sample)) -> Sample {
+        use compute::raw::*;
         match self {
-            Self::Tri   => compute::polyblep::tri(phase, dphase),
-            Self::Pulse => compute::polyblep::pulse(phase, dphase, tone),
-            Self::Saw   => compute::polyblep::saw(phase, dphase),
-            Self::Sine  => compute::raw::sine(phase),
+            Self::Pulse => {
+                step(0.0, 2.0);
+                step(tone, -2.0);
+                pulse(phase, tone)
+            }
+            Self::Saw => {
+                dual_steps(dual_offset(tone), -2.0, step);
+                dual(saw, phase, dual_offset(tone))
+            }
+            Self::SawDown => {
+                dual_steps(dual_offset(tone), 2.0, step);
+                dual(saw_down, phase, dual_offset(tone))
+            }
+            Self::Stepped => {
+                let steps = stepped_steps(tone);
+                let step_height = compute::step_height(steps);
+                step(0.0, -2.0);
+                for i in 1..steps as usize {
+                    step(i as Phase / steps, step_height);
+                }
+                stepped(phase, steps)
+            }
+            // everything else is continuous:
+            _ => self.compute_polyblep(phase, dphase, tone),
         }
     }
 }
 
+// each copy of a dual wave makes half of the jump, the second one `offset` earlier:
+fn dual_steps(offset: Phase, jump: Sample, step: &mut dyn FnMut(Phase, Sample)) {
+    step(0.0, jump * 0.5);
+    step((1.0 - offset) % 1.0, jump * 0.5);
+}
+
+// these map tone onto each shape's own parameter:
+
+fn dual_offset(tone: Scale) -> Phase {
+    tone - 0.5
+}
+
+fn trapezoid_rise(tone: Scale) -> Scale {
+    tone * 0.5
+}
+
+fn stepped_steps(tone: Scale) -> Scale {
+    (1.0 + 2.0 * tone).exp2().round()
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -73,6 +173,11 @@ mod test {
         assert_eq!(WaveShape::Pulse.into_u8(), PULSE);
         assert_eq!(WaveShape::Saw.into_u8(), SAW);
         assert_eq!(WaveShape::Sine.into_u8(), SINE);
+        assert_eq!(WaveShape::SawDown.into_u8(), SAW_DOWN);
+        assert_eq!(WaveShape::HalfSine.into_u8(), HALF_SINE);
+        assert_eq!(WaveShape::Parabolic.into_u8(), PARABOLIC);
+        assert_eq!(WaveShape::Trapezoid.into_u8(), TRAPEZOID);
+        assert_eq!(WaveShape::Stepped.into_u8(), STEPPED);
     }
     #[test]
     fn test_into_usize() {
@@ -80,13 +185,27 @@ mod test {
         assert_eq!(WaveShape::Pulse.into_usize(), PULSE as usize);
         assert_eq!(WaveShape::Saw.into_usize(), SAW as usize);
         assert_eq!(WaveShape::Sine.into_usize(), SINE as usize);
+        assert_eq!(WaveShape::Stepped.into_usize(), STEPPED as usize);
     }
     #[test]
-    fn test_from_u8() {
-        assert!(matches!(WaveShape::from_u8(TRI), WaveShape::Tri));
-        assert!(matches!(WaveShape::from_u8(PULSE), WaveShape::Pulse));
-        assert!(matches!(WaveShape::from_u8(SAW), WaveShape::Saw));
-        assert!(matches!(WaveShape::from_u8(SINE), WaveShape::Sine));
+    fn test_try_from_u8() {
+        assert!(matches!(WaveShape::try_from(TRI), Ok(WaveShape::Tri)));
+        assert!(matches!(WaveShape::try_from(PULSE), Ok(WaveShape::Pulse)));
+        assert!(matches!(WaveShape::try_from(SAW), Ok(WaveShape::Saw)));
+        assert!(matches!(WaveShape::try_from(SINE), Ok(WaveShape::Sine)));
+        assert!(matches!(WaveShape::try_from(SAW_DOWN), Ok(WaveShape::SawDown)));
+        assert!(matches!(WaveShape::try_from(HALF_SINE), Ok(WaveShape::HalfSine)));
+        assert!(matches!(WaveShape::try_from(PARABOLIC), Ok(WaveShape::Parabolic)));
+        assert!(matches!(WaveShape::try_from(TRAPEZOID), Ok(WaveShape::Trapezoid)));
+        assert!(matches!(WaveShape::try_from(STEPPED), Ok(WaveShape::Stepped)));
+        // every shape should round-trip:
+        for shape in SHAPES {
+            assert_eq!(WaveShape::try_from(shape.into_u8()).unwrap().into_u8(), shape.into_u8());
+        }
+        // out of range:
+        assert_eq!(WaveShape::try_from(9).unwrap_err(), InvalidWaveShape(9));
+        assert_eq!(WaveShape::try_from(u8::MAX).unwrap_err(), InvalidWaveShape(u8::MAX));
+        assert_eq!(InvalidWaveShape(9).to_string(), "WaveShape index 9 is out of range");
     }
     #[test]
     fn test_compute_aliasing() {
@@ -94,12 +213,39 @@ mod test {
         assert_eq!(WaveShape::Pulse.compute_aliasing(0.75, 0.5), -1.0);
         assert_eq!(WaveShape::Saw.compute_aliasing(0.42, 0.5), compute::raw::saw(0.42));
         assert_eq!(WaveShape::Sine.compute_aliasing(0.78, 0.5), compute::raw::sine(0.78));
+        assert_eq!(WaveShape::SawDown.compute_aliasing(0.42, 0.5), compute::raw::saw_down(0.42));
+        assert_eq!(WaveShape::HalfSine.compute_aliasing(0.78, 0.5), compute::raw::half_sine(0.78, 0.5));
+        assert_eq!(WaveShape::Parabolic.compute_aliasing(0.3, 0.5), compute::raw::parabolic(0.3));
+        assert_eq!(WaveShape::Trapezoid.compute_aliasing(0.3, 0.5), compute::raw::trapezoid(0.3, 0.25));
+        assert_eq!(WaveShape::Stepped.compute_aliasing(0.3, 0.5), compute::raw::stepped(0.3, 4.0));
     }
     #[test]
     fn test_compute_polyblep() {
-        assert_eq!(WaveShape::Tri.compute_polyblep(0.99, 0.1, 0.5), compute::polyblep::tri(0.99, 0.1));
-        assert_eq!(WaveShape::Pulse.compute_polyblep(0.99, 0.1, 0.5), compute::polyblep::pulse(0.99, 0.1, 0.5));
-        assert_eq!(WaveShape::Saw.compute_polyblep(0.99, 0.1, 0.5), compute::polyblep::saw(0.99, 0.1));
+        use compute::polyblep;
+        assert_eq!(WaveShape::Tri.compute_polyblep(0.99, 0.1, 0.5), polyblep::skewed_tri(0.99, 0.1, 0.5));
+        assert_eq!(WaveShape::Pulse.compute_polyblep(0.99, 0.1, 0.5), polyblep::pulse(0.99, 0.1, 0.5));
+        assert_eq!(WaveShape::Saw.compute_polyblep(0.99, 0.1, 0.5), polyblep::saw(0.99, 0.1));
         assert_eq!(WaveShape::Sine.compute_polyblep(0.99, 0.1, 0.5), compute::raw::sine(0.99));
+        assert_eq!(WaveShape::SawDown.compute_polyblep(0.99, 0.1, 0.5), polyblep::saw_down(0.99, 0.1));
+        assert_eq!(WaveShape::HalfSine.compute_polyblep(0.99, 0.1, 0.5), polyblep::half_sine(0.99, 0.1, 0.5));
+        assert_eq!(WaveShape::Parabolic.compute_polyblep(0.99, 0.1, 0.5), polyblep::parabolic(0.99, 0.1));
+        assert_eq!(WaveShape::Trapezoid.compute_polyblep(0.99, 0.1, 0.5), polyblep::trapezoid(0.99, 0.1, 0.25));
+        assert_eq!(WaveShape::Stepped.compute_polyblep(0.99, 0.1, 0.5), polyblep::stepped(0.99, 0.1, 4.0));
+    }
+    #[test]
+    fn test_tone() {
+        use compute::{raw, polyblep};
+        // tone should change every shape:
+        for shape in SHAPES {
+            assert_ne!(shape.compute_aliasing(0.3, 0.1), shape.compute_aliasing(0.3, 0.5));
+            assert_ne!(shape.compute_polyblep(0.3, 0.01, 0.1), shape.compute_polyblep(0.3, 0.01, 0.5));
+        }
+        assert_eq!(WaveShape::Tri.compute_aliasing(0.3, 0.2), raw::skewed_tri(0.3, 0.2));
+        assert_eq!(WaveShape::Sine.compute_aliasing(0.3, 0.2), raw::pd_sine(0.3, 0.2));
+        assert_eq!(WaveShape::Saw.compute_aliasing(0.3, 0.2), raw::dual(raw::saw, 0.3, -0.3));
+        assert_eq!(WaveShape::Saw.compute_polyblep(0.3, 0.1, 1.0), polyblep::dual(polyblep::saw, 0.3, 0.1, 0.5));
+        assert_eq!(WaveShape::Trapezoid.compute_aliasing(0.2, 0.0), raw::trapezoid(0.2, 0.0));
+        assert_eq!(WaveShape::Stepped.compute_aliasing(0.3, 0.0), raw::stepped(0.3, 2.0));
+        assert_eq!(WaveShape::Stepped.compute_aliasing(0.3, 1.0), raw::stepped(0.3, 8.0));
     }
 }